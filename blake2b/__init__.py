@@ -2,15 +2,69 @@
 Functions for calculating blake2b hashes.
 """
 from .blake2b import (  # noqa: F401
+    Blake2FParams,
+    Blake2b,
+    Blake2bParams,
+    Blake2bTree,
+    Blake2bp,
+    Blake2s,
+    Blake2xb,
+    CompressError,
+    InvalidBlockLengthError,
+    InvalidBlocksLengthError,
+    InvalidFinalBlockFlagError,
+    InvalidInputLengthError,
+    InvalidLastBlockLengthError,
+    InvalidOffsetCountersLengthError,
+    InvalidRoundsError,
+    InvalidStartingStateLengthError,
+    OutOfGasError,
+    RoundBudgetExceededError,
+    backend,
+    blake2f_precompile,
+    blake2s_compress,
     compress,
+    compress_blocks,
+    compress_in_place,
+    compress_into,
+    compress_with_last_node,
     decode_and_compress,
+    decode_and_compress_batch,
     decode_parameters,
+    encode_parameters,
 )
 
 __all__ = [
+    'Blake2FParams',
+    'Blake2b',
+    'Blake2bParams',
+    'Blake2bTree',
+    'Blake2bp',
+    'Blake2s',
+    'Blake2xb',
+    'CompressError',
+    'InvalidBlockLengthError',
+    'InvalidBlocksLengthError',
+    'InvalidFinalBlockFlagError',
+    'InvalidInputLengthError',
+    'InvalidLastBlockLengthError',
+    'InvalidOffsetCountersLengthError',
+    'InvalidRoundsError',
+    'InvalidStartingStateLengthError',
+    'OutOfGasError',
+    'RoundBudgetExceededError',
+    'backend',
+    'blake2f_precompile',
+    'blake2s_compress',
     'compress',
+    'compress_blocks',
+    'compress_in_place',
+    'compress_into',
+    'compress_with_last_node',
     'decode_and_compress',
+    'decode_and_compress_batch',
     'decode_parameters',
+    'encode_parameters',
     'test',
 ]
 
@@ -0,0 +1,206 @@
+use core::arch::x86_64::*;
+use core::ops::Range;
+
+use crate::backend::LANES;
+use crate::blake2b::{SIGMA, SIGMA_LEN};
+
+// Each row of the working vector is held in one 256-bit register.
+
+#[inline(always)]
+unsafe fn words(m: &[u64; 16], i: usize, j: usize, k: usize, l: usize) -> __m256i {
+    _mm256_set_epi64x(m[l] as i64, m[k] as i64, m[j] as i64, m[i] as i64)
+}
+
+/// Rotate the bits in each 64-bit word of `x` to the right by 32 bits.
+#[inline(always)]
+unsafe fn rotate_32(x: __m256i) -> __m256i {
+    _mm256_shuffle_epi32(x, 0b10_11_00_01)
+}
+
+/// Rotate the bits in each 64-bit word of `x` to the right by 24 or 16 bits with the byte shuffle
+/// `rotation`.
+#[inline(always)]
+unsafe fn rotate_bytes(x: __m256i, rotation: __m256i) -> __m256i {
+    _mm256_shuffle_epi8(x, rotation)
+}
+
+/// Rotate the bits in each 64-bit word of `x` to the right by 63 bits.
+#[inline(always)]
+unsafe fn rotate_63(x: __m256i) -> __m256i {
+    _mm256_xor_si256(_mm256_srli_epi64(x, 63), _mm256_add_epi64(x, x))
+}
+
+/// The blake2b mixing function G on all four columns or diagonals at once.
+///
+/// See here: https://tools.ietf.org/html/rfc7693#section-3.1
+#[allow(non_snake_case)]
+#[allow(clippy::too_many_arguments)]
+#[inline(always)]
+unsafe fn G(
+    a: &mut __m256i,
+    b: &mut __m256i,
+    c: &mut __m256i,
+    d: &mut __m256i,
+    x: __m256i,
+    y: __m256i,
+    rotate_24: __m256i,
+    rotate_16: __m256i,
+) {
+    *a = _mm256_add_epi64(_mm256_add_epi64(*a, *b), x);
+    *d = rotate_32(_mm256_xor_si256(*d, *a));
+    *c = _mm256_add_epi64(*c, *d);
+    *b = rotate_bytes(_mm256_xor_si256(*b, *c), rotate_24);
+    *a = _mm256_add_epi64(_mm256_add_epi64(*a, *b), y);
+    *d = rotate_bytes(_mm256_xor_si256(*d, *a), rotate_16);
+    *c = _mm256_add_epi64(*c, *d);
+    *b = rotate_63(_mm256_xor_si256(*b, *c));
+}
+
+/// Do rounds `rounds` of mixing of the message block `m` into the working vector `v` with AVX2
+/// instructions.
+///
+/// The caller must check that the CPU supports AVX2.
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn mix(v: &mut [u64; 16], m: &[u64; 16], rounds: Range<usize>) {
+    let rows = v.as_mut_ptr() as *mut __m256i;
+    let mut a = _mm256_loadu_si256(rows);
+    let mut b = _mm256_loadu_si256(rows.add(1));
+    let mut c = _mm256_loadu_si256(rows.add(2));
+    let mut d = _mm256_loadu_si256(rows.add(3));
+
+    #[rustfmt::skip]
+    let rotate_24 = _mm256_setr_epi8(
+        3, 4, 5, 6, 7, 0, 1, 2, 11, 12, 13, 14, 15, 8, 9, 10,
+        3, 4, 5, 6, 7, 0, 1, 2, 11, 12, 13, 14, 15, 8, 9, 10,
+    );
+    #[rustfmt::skip]
+    let rotate_16 = _mm256_setr_epi8(
+        2, 3, 4, 5, 6, 7, 0, 1, 10, 11, 12, 13, 14, 15, 8, 9,
+        2, 3, 4, 5, 6, 7, 0, 1, 10, 11, 12, 13, 14, 15, 8, 9,
+    );
+
+    for r in rounds {
+        let s = &SIGMA[r % SIGMA_LEN];
+
+        // Mix the columns.
+        #[rustfmt::skip]
+        G(&mut a, &mut b, &mut c, &mut d,
+          words(m, s[0], s[2], s[4], s[6]), words(m, s[1], s[3], s[5], s[7]),
+          rotate_24, rotate_16);
+
+        // Rotate the words of the last three rows to the left by one, two and three columns so
+        // that the diagonals line up as columns.
+        b = _mm256_permute4x64_epi64(b, 0b00_11_10_01);
+        c = _mm256_permute4x64_epi64(c, 0b01_00_11_10);
+        d = _mm256_permute4x64_epi64(d, 0b10_01_00_11);
+
+        // Mix the diagonals.
+        #[rustfmt::skip]
+        G(&mut a, &mut b, &mut c, &mut d,
+          words(m, s[8], s[10], s[12], s[14]), words(m, s[9], s[11], s[13], s[15]),
+          rotate_24, rotate_16);
+
+        // Rotate the words of the last three rows back into place.
+        b = _mm256_permute4x64_epi64(b, 0b10_01_00_11);
+        c = _mm256_permute4x64_epi64(c, 0b01_00_11_10);
+        d = _mm256_permute4x64_epi64(d, 0b00_11_10_01);
+    }
+
+    _mm256_storeu_si256(rows, a);
+    _mm256_storeu_si256(rows.add(1), b);
+    _mm256_storeu_si256(rows.add(2), c);
+    _mm256_storeu_si256(rows.add(3), d);
+}
+
+/// The blake2b mixing function G on words `a`, `b`, `c` and `d` of the working vectors held in the
+/// lanes of `w`.
+///
+/// See here: https://tools.ietf.org/html/rfc7693#section-3.1
+#[allow(non_snake_case)]
+#[allow(clippy::too_many_arguments)]
+#[inline(always)]
+unsafe fn G_lanes(
+    w: &mut [__m256i; 16],
+    a: usize,
+    b: usize,
+    c: usize,
+    d: usize,
+    x: __m256i,
+    y: __m256i,
+    rotate_24: __m256i,
+    rotate_16: __m256i,
+) {
+    let (mut wa, mut wb, mut wc, mut wd) = (w[a], w[b], w[c], w[d]);
+    G(
+        &mut wa, &mut wb, &mut wc, &mut wd, x, y, rotate_24, rotate_16,
+    );
+    w[a] = wa;
+    w[b] = wb;
+    w[c] = wc;
+    w[d] = wd;
+}
+
+/// Gather word `i` of each of the four vectors in `vectors` into the lanes of a register.
+#[inline(always)]
+unsafe fn gather(vectors: &[[u64; 16]; LANES], i: usize) -> __m256i {
+    _mm256_set_epi64x(
+        vectors[3][i] as i64,
+        vectors[2][i] as i64,
+        vectors[1][i] as i64,
+        vectors[0][i] as i64,
+    )
+}
+
+/// Do rounds `rounds` of mixing of the four message blocks `m` into the four working vectors `v`
+/// with AVX2 instructions, one instance of the compression function in each lane.  All 16 words of
+/// the working vectors are then mixed at once with no rotation between the columns and diagonals,
+/// and the independent instances are not held up by each other's dependency chains.
+///
+/// The caller must check that the CPU supports AVX2.
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn mix_lanes(
+    v: &mut [[u64; 16]; LANES],
+    m: &[[u64; 16]; LANES],
+    rounds: Range<usize>,
+) {
+    let mut w = [_mm256_setzero_si256(); 16];
+    let mut words = [_mm256_setzero_si256(); 16];
+    for i in 0..16 {
+        w[i] = gather(v, i);
+        words[i] = gather(m, i);
+    }
+
+    #[rustfmt::skip]
+    let rotate_24 = _mm256_setr_epi8(
+        3, 4, 5, 6, 7, 0, 1, 2, 11, 12, 13, 14, 15, 8, 9, 10,
+        3, 4, 5, 6, 7, 0, 1, 2, 11, 12, 13, 14, 15, 8, 9, 10,
+    );
+    #[rustfmt::skip]
+    let rotate_16 = _mm256_setr_epi8(
+        2, 3, 4, 5, 6, 7, 0, 1, 10, 11, 12, 13, 14, 15, 8, 9,
+        2, 3, 4, 5, 6, 7, 0, 1, 10, 11, 12, 13, 14, 15, 8, 9,
+    );
+
+    for r in rounds {
+        let s = &SIGMA[r % SIGMA_LEN];
+        let m = |i: usize| words[s[i]];
+
+        G_lanes(&mut w, 0, 4, 8, 12, m(0), m(1), rotate_24, rotate_16);
+        G_lanes(&mut w, 1, 5, 9, 13, m(2), m(3), rotate_24, rotate_16);
+        G_lanes(&mut w, 2, 6, 10, 14, m(4), m(5), rotate_24, rotate_16);
+        G_lanes(&mut w, 3, 7, 11, 15, m(6), m(7), rotate_24, rotate_16);
+
+        G_lanes(&mut w, 0, 5, 10, 15, m(8), m(9), rotate_24, rotate_16);
+        G_lanes(&mut w, 1, 6, 11, 12, m(10), m(11), rotate_24, rotate_16);
+        G_lanes(&mut w, 2, 7, 8, 13, m(12), m(13), rotate_24, rotate_16);
+        G_lanes(&mut w, 3, 4, 9, 14, m(14), m(15), rotate_24, rotate_16);
+    }
+
+    let mut lanes = [0u64; LANES];
+    for (i, word) in w.iter().enumerate() {
+        _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, *word);
+        for (vector, lane) in v.iter_mut().zip(lanes.iter()) {
+            vector[i] = *lane;
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use core::convert::TryInto;
+use core::fmt;
+use core::ops::Range;
+
+use crate::scalar;
+#[cfg(target_arch = "x86_64")]
+use crate::{avx2, sse41};
+
+/// The number of independent instances of the compression function F that `Backend::mix_lanes`
+/// mixes at once.
+pub const LANES: usize = 4;
+
+/// An implementation of the rounds of mixing done by the blake2b compression function F.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// Portable code, which mixes one column or diagonal of the working vector at a time.
+    Scalar,
+    /// SSE4.1 instructions, which mix two columns or diagonals of the working vector at a time.
+    Sse41,
+    /// AVX2 instructions, which mix all four columns or diagonals of the working vector at once.
+    Avx2,
+}
+
+impl Backend {
+    /// Every backend, from the slowest to the fastest.
+    pub const ALL: [Backend; 3] = [Backend::Scalar, Backend::Sse41, Backend::Avx2];
+
+    /// The name of the backend, as returned by the Python `backend` function.
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Scalar => "scalar",
+            Backend::Sse41 => "sse4.1",
+            Backend::Avx2 => "avx2",
+        }
+    }
+
+    /// Whether the backend is supported by the CPU that the process is running on.  Without the
+    /// `std` feature, the CPU can't be queried and only the features enabled at compile time are
+    /// taken to be supported.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Backend::Scalar => true,
+            #[cfg(all(target_arch = "x86_64", feature = "std"))]
+            Backend::Sse41 => std::is_x86_feature_detected!("sse4.1"),
+            #[cfg(all(target_arch = "x86_64", feature = "std"))]
+            Backend::Avx2 => std::is_x86_feature_detected!("avx2"),
+            #[cfg(all(target_arch = "x86_64", not(feature = "std")))]
+            Backend::Sse41 => cfg!(target_feature = "sse4.1"),
+            #[cfg(all(target_arch = "x86_64", not(feature = "std")))]
+            Backend::Avx2 => cfg!(target_feature = "avx2"),
+            #[cfg(not(target_arch = "x86_64"))]
+            _ => false,
+        }
+    }
+
+    /// Do rounds `rounds` of mixing of the message block `m` into the working vector `v`.
+    ///
+    /// Panics if the backend is not supported by the CPU.
+    pub(crate) fn mix(self, v: &mut [u64; 16], m: &[u64], rounds: Range<usize>) {
+        assert!(self.is_supported(), "{} backend is not supported", self);
+        let m: &[u64; 16] = m[..16].try_into().unwrap();
+        #[cfg(test)]
+        tests::count_rounds(rounds.len());
+
+        match self {
+            // The CPU was checked to support the instructions used by each backend above.
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx2 => unsafe { avx2::mix(v, m, rounds) },
+            #[cfg(target_arch = "x86_64")]
+            Backend::Sse41 => unsafe { sse41::mix(v, m, rounds) },
+            _ => scalar::mix(v, m, rounds),
+        }
+    }
+
+    /// Do rounds `rounds` of mixing of the independent message blocks in `m` into the matching
+    /// working vectors in `v`, for each of the lanes numbered in `active`.  The AVX2 backend mixes
+    /// one instance in each lane of its registers, so every lane is mixed at once whether or not
+    /// it is active.  The other backends mix only the active instances, one after another.
+    ///
+    /// Panics if the backend is not supported by the CPU.
+    pub(crate) fn mix_lanes(
+        self,
+        v: &mut [[u64; 16]; LANES],
+        m: &[[u64; 16]; LANES],
+        active: &[usize],
+        rounds: Range<usize>,
+    ) {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx2 => {
+                assert!(self.is_supported(), "{} backend is not supported", self);
+                #[cfg(test)]
+                tests::count_rounds(rounds.len());
+                // The CPU was checked to support AVX2 above.
+                unsafe { avx2::mix_lanes(v, m, rounds) }
+            }
+            _ => {
+                for i in active {
+                    self.mix(&mut v[*i], &m[*i], rounds.clone());
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The backend used by the compression function F, which is the fastest backend supported by the
+/// CPU, with CPU features detected at runtime and the scalar backend as the fallback.
+pub fn backend() -> Backend {
+    Backend::ALL
+        .iter()
+        .rev()
+        .copied()
+        .find(|backend| backend.is_supported())
+        .unwrap_or(Backend::Scalar)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+
+    thread_local! {
+        /// The number of rounds of mixing done on this thread, with a round of all lanes at once
+        /// counted as one.
+        static ROUNDS_MIXED: Cell<usize> = const { Cell::new(0) };
+    }
+
+    pub(super) fn count_rounds(rounds: usize) {
+        ROUNDS_MIXED.with(|mixed| mixed.set(mixed.get() + rounds));
+    }
+
+    /// The number of rounds of mixing done on this thread by `f`.
+    pub(crate) fn rounds_mixed(f: impl FnOnce()) -> usize {
+        let start = ROUNDS_MIXED.with(Cell::get);
+        f();
+
+        ROUNDS_MIXED.with(Cell::get) - start
+    }
+
+    use crate::blake2b::IV;
+
+    #[test]
+    fn test_backends_are_equivalent() {
+        let m: Vec<u64> = (0..16u64)
+            .map(|i| i.wrapping_mul(0x9e3779b97f4a7c15))
+            .collect();
+        let mut start = [0u64; 16];
+        start[..8].copy_from_slice(&IV);
+        start[8..].copy_from_slice(&IV);
+
+        let mut expected = start;
+        Backend::Scalar.mix(&mut expected, &m, 3..40);
+
+        for backend in Backend::ALL.iter().filter(|b| b.is_supported()) {
+            let mut v = start;
+            backend.mix(&mut v, &m, 3..40);
+
+            assert_eq!(v, expected, "{} backend", backend);
+        }
+    }
+
+    #[test]
+    fn test_backend_lanes_are_equivalent() {
+        let mut v = [[0u64; 16]; LANES];
+        let mut m = [[0u64; 16]; LANES];
+        for (i, (v, m)) in v.iter_mut().zip(m.iter_mut()).enumerate() {
+            for j in 0..16 {
+                v[j] = (i as u64 * 16 + j as u64).wrapping_mul(0x9e3779b97f4a7c15);
+                m[j] = (j as u64 * 16 + i as u64).wrapping_mul(0xbf58476d1ce4e5b9);
+            }
+        }
+
+        let mut expected = v;
+        for (v, m) in expected.iter_mut().zip(m.iter()) {
+            Backend::Scalar.mix(v, m, 7..31);
+        }
+
+        for backend in Backend::ALL.iter().filter(|b| b.is_supported()) {
+            let mut lanes = v;
+            backend.mix_lanes(&mut lanes, &m, &[0, 1, 2, 3], 7..31);
+
+            assert_eq!(lanes, expected, "{} backend", backend);
+        }
+    }
+
+    #[test]
+    fn test_backend_selection() {
+        let detected = backend();
+
+        assert!(detected.is_supported());
+        assert!(Backend::ALL
+            .iter()
+            .skip_while(|b| **b != detected)
+            .skip(1)
+            .all(|b| !b.is_supported()));
+    }
+}
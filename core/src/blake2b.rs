@@ -0,0 +1,1069 @@
+use core::convert::{TryFrom, TryInto};
+
+use crate::backend::{backend, Backend, LANES};
+use crate::error::{check_vector_lengths, CompressError, FINAL_BLOCK_FLAG_OFFSET, INPUT_LENGTH};
+
+/// The number of bytes in a blake2b message block.
+pub const BLOCKBYTES: usize = 128;
+
+/// The number of rounds after which the message schedule `SIGMA` repeats.
+pub const SIGMA_LEN: usize = 10;
+
+/// The message schedule, giving the order in which the words of the message block are mixed in
+/// each round.  This is shared by blake2b and blake2s.
+///
+/// See here: https://tools.ietf.org/html/rfc7693#section-2.7
+pub const SIGMA: [[usize; 16]; SIGMA_LEN] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+const MASKBITS: u64 = u64::MAX;
+
+/// The blake2b initialization vector, which the parameter block is XORed into to give the starting
+/// state of a hash.
+pub const IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+#[inline]
+fn u64_from_le(input: &[u8]) -> u64 {
+    u64::from_le_bytes(input.try_into().unwrap())
+}
+
+#[inline]
+pub(crate) fn eight_words(input: &[u8]) -> [u64; 8] {
+    [
+        u64_from_le(&input[..8]),
+        u64_from_le(&input[8..16]),
+        u64_from_le(&input[16..24]),
+        u64_from_le(&input[24..32]),
+        u64_from_le(&input[32..40]),
+        u64_from_le(&input[40..48]),
+        u64_from_le(&input[48..56]),
+        u64_from_le(&input[56..64]),
+    ]
+}
+
+#[inline]
+pub(crate) fn sixteen_words(input: &[u8]) -> [u64; 16] {
+    [
+        u64_from_le(&input[..8]),
+        u64_from_le(&input[8..16]),
+        u64_from_le(&input[16..24]),
+        u64_from_le(&input[24..32]),
+        u64_from_le(&input[32..40]),
+        u64_from_le(&input[40..48]),
+        u64_from_le(&input[48..56]),
+        u64_from_le(&input[56..64]),
+        u64_from_le(&input[64..72]),
+        u64_from_le(&input[72..80]),
+        u64_from_le(&input[80..88]),
+        u64_from_le(&input[88..96]),
+        u64_from_le(&input[96..104]),
+        u64_from_le(&input[104..112]),
+        u64_from_le(&input[112..120]),
+        u64_from_le(&input[120..128]),
+    ]
+}
+
+#[inline]
+fn two_words(input: &[u8]) -> [u64; 2] {
+    [u64_from_le(&input[..8]), u64_from_le(&input[8..16])]
+}
+
+/// The eight 64-bit words of blake2b state, either the starting state h passed to the compression
+/// function F or the state that it returns.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct State(pub [u64; 8]);
+
+impl State {
+    /// Read the state from its 64 little-endian bytes.
+    pub fn from_bytes(bytes: &[u8; 64]) -> Self {
+        State(eight_words(bytes))
+    }
+
+    /// The 64 little-endian bytes of the state, which are the output of the blake2 F precompile
+    /// and, truncated to the digest length, the hash of a message after its final block.
+    pub fn to_bytes(&self) -> [u8; 64] {
+        state_to_bytes(&self.0)
+    }
+}
+
+impl TryFrom<&[u64]> for State {
+    type Error = CompressError;
+
+    fn try_from(words: &[u64]) -> Result<Self, CompressError> {
+        words
+            .try_into()
+            .map(State)
+            .map_err(|_| CompressError::InvalidStartingStateLength {
+                length: words.len(),
+            })
+    }
+}
+
+/// The sixteen 64-bit words of a 128-byte message block m passed to the compression function F.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Block(pub [u64; 16]);
+
+impl Block {
+    /// Read the block from its 128 little-endian bytes.  The last block of a message is padded
+    /// with zeros.
+    pub fn from_bytes(bytes: &[u8; 128]) -> Self {
+        Block(sixteen_words(bytes))
+    }
+}
+
+impl TryFrom<&[u64]> for Block {
+    type Error = CompressError;
+
+    fn try_from(words: &[u64]) -> Result<Self, CompressError> {
+        words
+            .try_into()
+            .map(Block)
+            .map_err(|_| CompressError::InvalidBlockLength {
+                length: words.len(),
+            })
+    }
+}
+
+/// The 128-bit offset counter t passed to the compression function F, which is the number of
+/// message bytes up to and including the current block.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Counter(pub u128);
+
+impl Counter {
+    /// The low and high 64-bit words of the counter, which are t0 and t1 in RFC 7693.
+    pub fn words(self) -> [u64; 2] {
+        [self.0 as u64, (self.0 >> 64) as u64]
+    }
+}
+
+impl From<[u64; 2]> for Counter {
+    fn from(words: [u64; 2]) -> Self {
+        Counter(words[0] as u128 | (words[1] as u128) << 64)
+    }
+}
+
+impl TryFrom<&[u64]> for Counter {
+    type Error = CompressError;
+
+    fn try_from(words: &[u64]) -> Result<Self, CompressError> {
+        <[u64; 2]>::try_from(words).map(Counter::from).map_err(|_| {
+            CompressError::InvalidOffsetCountersLength {
+                length: words.len(),
+            }
+        })
+    }
+}
+
+/// Run `rounds` rounds of the blake2b compression function F, mixing the message `block` into
+/// the starting `state` with the offset `counter`.  `final_block` is the final block indicator
+/// flag f, which must be set for the last block of a message and only for that block.  Any
+/// number of rounds is allowed, with standard blake2b using 12, and the rounds of mixing are done
+/// with the fastest backend supported by the CPU.
+///
+/// See here: https://tools.ietf.org/html/rfc7693#section-3.2
+///
+/// # Examples
+///
+/// Hash the message "abc" with unkeyed blake2b-512:
+///
+/// ```
+/// use blake2b_core::{compress, Block, Counter, State, IV};
+///
+/// let mut state = State(IV);
+/// state.0[0] ^= 0x01010040;
+///
+/// let mut block = [0u8; 128];
+/// block[..3].copy_from_slice(b"abc");
+///
+/// let state = compress(12, &state, &Block::from_bytes(&block), Counter(3), true);
+///
+/// assert_eq!(state.to_bytes()[..4], [0xba, 0x80, 0xa5, 0x3f]);
+/// ```
+pub fn compress(
+    rounds: usize,
+    state: &State,
+    block: &Block,
+    counter: Counter,
+    final_block: bool,
+) -> State {
+    compress_with_last_node(rounds, state, block, counter, final_block, false)
+}
+
+/// Like `compress` except the last node flag f1 may also be set, as needed for the final block of
+/// the last node at each level of a hash tree.
+///
+/// See here: https://blake2.net/blake2.pdf (section 2.3)
+pub fn compress_with_last_node(
+    rounds: usize,
+    state: &State,
+    block: &Block,
+    counter: Counter,
+    final_block: bool,
+    last_node: bool,
+) -> State {
+    State(compress_state(
+        rounds,
+        &state.0,
+        &block.0,
+        &counter.words(),
+        final_block,
+        last_node,
+    ))
+}
+
+/// The parameters of the blake2b compression function F, as encoded in the tightly packed input
+/// of the blake2 F precompile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Blake2FParams {
+    /// The number of rounds of mixing.
+    pub rounds: usize,
+    /// The starting state vector h.
+    pub state: [u64; 8],
+    /// The message block vector m.
+    pub block: [u64; 16],
+    /// The offset counters vector t.
+    pub offset_counters: [u64; 2],
+    /// The final block indicator flag f.
+    pub final_block_flag: bool,
+}
+
+impl Blake2FParams {
+    /// Encode the parameters into the tightly packed 213-byte encoding accepted by
+    /// `decode_parameters`.
+    pub fn encode(&self) -> Result<[u8; INPUT_LENGTH], CompressError> {
+        encode_parameters(
+            self.rounds,
+            &self.state,
+            &self.block,
+            &self.offset_counters,
+            self.final_block_flag,
+        )
+    }
+
+    /// Run the compression function F on the parameters.
+    pub fn compress(&self) -> [u8; 64] {
+        F(
+            self.rounds,
+            &self.state,
+            &self.block,
+            &self.offset_counters,
+            self.final_block_flag,
+        )
+    }
+}
+
+/// Decode blake2 precompile input parameters from the tightly packed encoding in the byte sequence
+/// `input`.
+///
+/// See here: https://eips.ethereum.org/EIPS/eip-152#specification
+pub fn decode_parameters(input: &[u8]) -> Result<Blake2FParams, CompressError> {
+    if input.len() != INPUT_LENGTH {
+        return Err(CompressError::InvalidInputLength {
+            length: input.len(),
+        });
+    }
+
+    let rounds = u32::from_be_bytes((&input[..4]).try_into().unwrap()) as usize;
+    let state = eight_words(&input[4..68]);
+    let block = sixteen_words(&input[68..196]);
+    let offset_counters = two_words(&input[196..212]);
+    let final_block_flag = match input[FINAL_BLOCK_FLAG_OFFSET] {
+        0 => false,
+        1 => true,
+        x => {
+            return Err(CompressError::InvalidFinalBlockFlag {
+                value: x,
+                offset: FINAL_BLOCK_FLAG_OFFSET,
+            });
+        }
+    };
+
+    Ok(Blake2FParams {
+        rounds,
+        state,
+        block,
+        offset_counters,
+        final_block_flag,
+    })
+}
+
+/// Encode blake2 precompile input parameters into the tightly packed 213-byte encoding accepted
+/// by `decode_parameters`.  The rounds are big-endian and all words are little-endian.
+///
+/// See here: https://eips.ethereum.org/EIPS/eip-152#specification
+pub fn encode_parameters(
+    rounds: usize,
+    starting_state: &[u64],
+    block: &[u64],
+    offset_counters: &[u64],
+    final_block_flag: bool,
+) -> Result<[u8; INPUT_LENGTH], CompressError> {
+    let encoded_rounds =
+        u32::try_from(rounds).map_err(|_| CompressError::InvalidRounds { rounds })?;
+    check_vector_lengths(starting_state.len(), block.len(), offset_counters.len())?;
+
+    let mut input = [0u8; INPUT_LENGTH];
+    input[..4].copy_from_slice(&encoded_rounds.to_be_bytes());
+    let words = starting_state
+        .iter()
+        .chain(block.iter())
+        .chain(offset_counters.iter());
+    for (chunk, word) in input[4..FINAL_BLOCK_FLAG_OFFSET]
+        .chunks_exact_mut(8)
+        .zip(words)
+    {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    input[FINAL_BLOCK_FLAG_OFFSET] = final_block_flag as u8;
+
+    Ok(input)
+}
+
+/// Initialize the working vector of the compression function F.
+///
+/// See here: https://tools.ietf.org/html/rfc7693#section-3.2
+#[inline]
+pub(crate) fn working_vector(
+    h: &[u64],
+    t: &[u64],
+    final_block_flag: bool,
+    last_node_flag: bool,
+) -> [u64; 16] {
+    [
+        h[0],         // 0
+        h[1],         // 1
+        h[2],         // 2
+        h[3],         // 3
+        h[4],         // 4
+        h[5],         // 5
+        h[6],         // 6
+        h[7],         // 7
+        IV[0],        // 8
+        IV[1],        // 9
+        IV[2],        // 10
+        IV[3],        // 11
+        IV[4] ^ t[0], // 12
+        IV[5] ^ t[1], // 13
+        if final_block_flag {
+            MASKBITS ^ IV[6]
+        } else {
+            IV[6]
+        }, // 14
+        if last_node_flag {
+            MASKBITS ^ IV[7]
+        } else {
+            IV[7]
+        }, // 15
+    ]
+}
+
+/// XOR the two halves of the working vector `v` into the starting state `h`.
+#[inline]
+pub(crate) fn chain_value(h: &[u64], v: &[u64; 16]) -> [u64; 8] {
+    [
+        h[0] ^ v[0] ^ v[8],
+        h[1] ^ v[1] ^ v[9],
+        h[2] ^ v[2] ^ v[10],
+        h[3] ^ v[3] ^ v[11],
+        h[4] ^ v[4] ^ v[12],
+        h[5] ^ v[5] ^ v[13],
+        h[6] ^ v[6] ^ v[14],
+        h[7] ^ v[7] ^ v[15],
+    ]
+}
+
+/// The blake2b compression function F, returning the resulting state vector as words rather than
+/// serialized bytes.  The `last_node_flag` is the second finalization flag f1 used by the tree
+/// hashing modes, which inverts the last word of the working vector.
+///
+/// See here: https://tools.ietf.org/html/rfc7693#section-3.2
+pub(crate) fn compress_state(
+    rounds: usize,
+    starting_state: &[u64],
+    block: &[u64],
+    offset_counters: &[u64],
+    final_block_flag: bool,
+    last_node_flag: bool,
+) -> [u64; 8] {
+    let h = starting_state;
+    let m = block;
+    let t = offset_counters;
+
+    let mut v = working_vector(h, t, final_block_flag, last_node_flag);
+    backend().mix(&mut v, m, 0..rounds);
+
+    chain_value(h, &v)
+}
+
+/// The blake2b compression function F.
+///
+/// See here: https://tools.ietf.org/html/rfc7693#section-3.2
+#[allow(non_snake_case)]
+pub(crate) fn F(
+    rounds: usize,
+    starting_state: &[u64],
+    block: &[u64],
+    offset_counters: &[u64],
+    final_block_flag: bool,
+) -> [u8; 64] {
+    state_to_bytes(&compress_state(
+        rounds,
+        starting_state,
+        block,
+        offset_counters,
+        final_block_flag,
+        false,
+    ))
+}
+
+/// Run the blake2b compression function F on each 128-byte block in `blocks` in turn, chaining
+/// the state from `starting_state`.  The offset counters are advanced from `offset` by 128 bytes
+/// for each block except the last, which adds `last_block_length` bytes and is the only block
+/// compressed with the final block flag set.
+///
+/// See here: https://tools.ietf.org/html/rfc7693#section-3.3
+pub fn compress_blocks(
+    rounds: usize,
+    starting_state: &[u64],
+    blocks: &[u8],
+    offset: u128,
+    last_block_length: usize,
+) -> Result<[u8; 64], CompressError> {
+    if blocks.is_empty() || !blocks.len().is_multiple_of(BLOCKBYTES) {
+        return Err(CompressError::InvalidBlocksLength {
+            length: blocks.len(),
+        });
+    }
+    if last_block_length > BLOCKBYTES {
+        return Err(CompressError::InvalidLastBlockLength {
+            length: last_block_length,
+        });
+    }
+    let mut h: [u64; 8] =
+        starting_state
+            .try_into()
+            .map_err(|_| CompressError::InvalidStartingStateLength {
+                length: starting_state.len(),
+            })?;
+
+    let block_count = blocks.len() / BLOCKBYTES;
+    let mut t = offset;
+    for (i, block) in blocks.chunks_exact(BLOCKBYTES).enumerate() {
+        let final_block_flag = i + 1 == block_count;
+        t = t.wrapping_add(if final_block_flag {
+            last_block_length
+        } else {
+            BLOCKBYTES
+        } as u128);
+
+        h = compress_state(
+            rounds,
+            &h,
+            &sixteen_words(block),
+            &[t as u64, (t >> 64) as u64],
+            final_block_flag,
+            false,
+        );
+    }
+
+    Ok(state_to_bytes(&h))
+}
+
+/// Run the compression function F on up to `LANES` independent sets of parameters at once, with
+/// one instance in each SIMD lane where the backend supports it.  The result for each set of
+/// parameters is returned at the same index, and any parameters after the first `LANES` are
+/// ignored.  The lanes are mixed together until the instance with the fewest rounds is done, and
+/// so on, with the last instance left running finished on its own.  Backends without SIMD lanes
+/// only mix the instances which are not done yet, so no more rounds are mixed than by
+/// compressing each instance separately.
+pub fn compress_lanes(params: &[Blake2FParams]) -> [[u8; 64]; LANES] {
+    compress_lanes_with(backend(), params)
+}
+
+/// `compress_lanes` with the rounds of mixing done by `backend`.
+pub(crate) fn compress_lanes_with(backend: Backend, params: &[Blake2FParams]) -> [[u8; 64]; LANES] {
+    let params = &params[..params.len().min(LANES)];
+    let mut v = [[0u64; 16]; LANES];
+    let mut m = [[0u64; 16]; LANES];
+    for (i, p) in params.iter().enumerate() {
+        v[i] = working_vector(&p.state, &p.offset_counters, p.final_block_flag, false);
+        m[i] = p.block;
+    }
+
+    let mut order: [usize; LANES] = core::array::from_fn(|i| i);
+    let order = &mut order[..params.len()];
+    order.sort_unstable_by_key(|i| params[*i].rounds);
+    let order = &*order;
+
+    let mut results = [[0u8; 64]; LANES];
+    let mut rounds_done = 0;
+    for (k, i) in order.iter().copied().enumerate() {
+        let rounds = params[i].rounds;
+        if k + 1 == order.len() {
+            backend.mix(&mut v[i], &m[i], rounds_done..rounds);
+        } else {
+            // The instances from this one on are not done yet.
+            backend.mix_lanes(&mut v, &m, &order[k..], rounds_done..rounds);
+        }
+        rounds_done = rounds;
+
+        results[i] = state_to_bytes(&chain_value(&params[i].state, &v[i]));
+    }
+
+    results
+}
+
+/// Like `compress` except `check` is called with the number of rounds done so far after every
+/// `check_interval` rounds until all rounds are done.  If `check` returns an error, the
+/// computation is abandoned and the error is returned.  This allows computations with up to
+/// 2 ** 32 - 1 rounds to be interrupted.  A `check_interval` of zero is treated as one.
+pub fn compress_with_checks<E>(
+    rounds: usize,
+    state: &State,
+    block: &Block,
+    counter: Counter,
+    final_block: bool,
+    check_interval: usize,
+    mut check: impl FnMut(usize) -> Result<(), E>,
+) -> Result<State, E> {
+    let check_interval = check_interval.max(1);
+    let backend = backend();
+
+    let mut v = working_vector(&state.0, &counter.words(), final_block, false);
+    let mut rounds_done = 0;
+    while rounds_done < rounds {
+        if rounds_done > 0 {
+            check(rounds_done)?;
+        }
+
+        let end = rounds_done + check_interval.min(rounds - rounds_done);
+        backend.mix(&mut v, &block.0, rounds_done..end);
+        rounds_done = end;
+    }
+
+    Ok(State(chain_value(&state.0, &v)))
+}
+
+fn state_to_bytes(state: &[u64; 8]) -> [u8; 64] {
+    let mut result = [0u8; 64];
+    for (i, word) in state.iter().enumerate() {
+        result[i * 8..(i + 1) * 8].copy_from_slice(&word.to_le_bytes());
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAST_EXAMPLES: &[(&str, &str)] = &[
+        (
+            "0000000048c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001",
+            "08c9bcf367e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d282e6ad7f520e511f6c3e2b8c68059b9442be0454267ce079217e1319cde05b",
+        ),
+        (
+            "0000000c48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001",
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923",
+        ),
+        (
+            "0000000c48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000000",
+            "75ab69d3190a562c51aef8d88f1c2775876944407270c42c9844252c26d2875298743e7f6d5ea2f2d3e8d226039cd31b4e426ac4f2d3d666a610c2116fde4735",
+        ),
+        (
+            "0000000148c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001",
+            "b63a380cb2897d521994a85234ee2c181b5f844d2c624c002677e9703449d2fba551b3a8333bcdf5f2f7e08993d53923de3d64fcc68c034e717b9293fed7a421",
+        ),
+    ];
+
+    const ERROR_EXAMPLES: &[&str] = &[
+        "",
+        "00000c48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001",
+        "000000000c48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001",
+        "0000000c48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000002",
+    ];
+
+    const SLOW_EXAMPLES: &[(&str, &str)] = &[
+        ( // 2,000,000 rounds
+            "001e848048c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001",
+            "a86f2348a6afc9a7ccb3ae6e92818eb34f57f4e0d618580efa1c9b0a35ea84998c22afe92c41e4b538f213f8f35deb37e47fc6a8eca34f645da18231f59c6190",
+        ),
+        ( // 8,000,000 rounds
+            "007a120048c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001",
+            "6d2ce9e534d50e18ff866ae92d70cceba79bbcd14c63819fe48752c8aca87a4bb7dcc230d22a4047f0486cfcfb50a17b24b2899eb8fca370f22240adb5170189",
+        ),
+    ];
+
+    #[test]
+    fn test_f_success() {
+        for (inp, expected) in FAST_EXAMPLES {
+            let input_bytes = hex::decode(inp).unwrap();
+            let Blake2FParams {
+                rounds,
+                state: starting_state,
+                block,
+                offset_counters,
+                final_block_flag,
+            } = decode_parameters(&input_bytes).unwrap();
+
+            let result_bytes = F(
+                rounds,
+                &starting_state,
+                &block,
+                &offset_counters,
+                final_block_flag,
+            )
+            .to_vec();
+
+            assert_eq!(hex::encode(result_bytes), *expected);
+        }
+    }
+
+    #[test]
+    fn test_f_slow() {
+        for (inp, expected) in SLOW_EXAMPLES {
+            let input_bytes = hex::decode(inp).unwrap();
+            let Blake2FParams {
+                rounds,
+                state: starting_state,
+                block,
+                offset_counters,
+                final_block_flag,
+            } = decode_parameters(&input_bytes).unwrap();
+
+            let result_bytes = F(
+                rounds,
+                &starting_state,
+                &block,
+                &offset_counters,
+                final_block_flag,
+            )
+            .to_vec();
+
+            assert_eq!(hex::encode(result_bytes), *expected);
+        }
+    }
+
+    /// Check slow running test vector 8 from EIP 152
+    /// (https://eips.ethereum.org/EIPS/eip-152#test-vector-8)
+    #[test]
+    #[ignore]
+    fn test_f_eip_152_vec_8() {
+        let (inp, expected) = ( // 2 ** 32 - 1 rounds
+            "ffffffff48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001",
+            "fc59093aafa9ab43daae0e914c57635c5402d8e3d2130eb9b3cc181de7f0ecf9b22bf99a7815ce16419e200e01846e6b5df8cc7703041bbceb571de6631d2615",
+        );
+
+        let input_bytes = hex::decode(inp).unwrap();
+        let Blake2FParams {
+            rounds,
+            state: starting_state,
+            block,
+            offset_counters,
+            final_block_flag,
+        } = decode_parameters(&input_bytes).unwrap();
+
+        let t_start = std::time::SystemTime::now();
+
+        let result_bytes = F(
+            rounds,
+            &starting_state,
+            &block,
+            &offset_counters,
+            final_block_flag,
+        )
+        .to_vec();
+
+        if let Ok(elapsed) = t_start.elapsed() {
+            eprintln!("test_f_eip_152_vec_8: took {} secs", elapsed.as_secs());
+        }
+
+        assert_eq!(hex::encode(result_bytes), *expected);
+    }
+
+    #[test]
+    fn test_compress_with_last_node() {
+        let mut starting_state = IV;
+        starting_state[0] ^= 0x0101_0040;
+        let mut block = [0u64; 16];
+        block[0] = 0x0063_6261;
+
+        let (state, block) = (State(starting_state), Block(block));
+
+        assert_eq!(
+            compress_with_last_node(12, &state, &block, Counter(3), true, false).to_bytes()[..],
+            F(12, &starting_state, &block.0, &[3, 0], true)[..],
+        );
+        // The digest of "abc" from Python's `hashlib.blake2b` with `last_node=True`.
+        assert_eq!(
+            hex::encode(
+                &compress_with_last_node(12, &state, &block, Counter(3), true, true).to_bytes()[..]
+            ),
+            "0c72c218c5d1c50f3f4abb0645c1a1178c901c6995d3e2cb70c3c5572c9ad1fa4bdc2d8f59db5ab0debce9ed4c043ed2713954b333ca07b815d91218ac3e3de4",
+        );
+    }
+
+    #[test]
+    fn test_compress() {
+        for (inp, expected) in FAST_EXAMPLES {
+            let params = decode_parameters(&hex::decode(inp).unwrap()).unwrap();
+
+            let result = compress(
+                params.rounds,
+                &State(params.state),
+                &Block(params.block),
+                Counter::from(params.offset_counters),
+                params.final_block_flag,
+            );
+
+            assert_eq!(hex::encode(&result.to_bytes()[..]), *expected);
+            assert_eq!(result, State::from_bytes(&result.to_bytes()));
+        }
+    }
+
+    #[test]
+    fn test_compress_types_from_slices() {
+        let words: Vec<u64> = (0..17).collect();
+
+        assert_eq!(
+            State::try_from(&words[..8]),
+            Ok(State([0, 1, 2, 3, 4, 5, 6, 7])),
+        );
+        assert_eq!(
+            State::try_from(&words[..9]),
+            Err(CompressError::InvalidStartingStateLength { length: 9 }),
+        );
+        assert_eq!(Block::try_from(&words[1..]).unwrap().0[15], 16);
+        assert_eq!(
+            Block::try_from(&words[..]),
+            Err(CompressError::InvalidBlockLength { length: 17 }),
+        );
+        assert_eq!(Counter::try_from(&[3, 1][..]), Ok(Counter(3 | 1 << 64)),);
+        assert_eq!(
+            Counter::try_from(&words[..0]),
+            Err(CompressError::InvalidOffsetCountersLength { length: 0 }),
+        );
+        assert_eq!(Counter(u128::MAX - 1).words(), [u64::MAX - 1, u64::MAX]);
+
+        let mut bytes = [0u8; 128];
+        bytes[0] = 0x61;
+        bytes[127] = 0x80;
+        let block = Block::from_bytes(&bytes);
+        assert_eq!(block.0[0], 0x61);
+        assert_eq!(block.0[15], 0x80 << 56);
+    }
+
+    #[test]
+    fn test_compress_lanes_rounds_mixed() {
+        let base = decode_parameters(&hex::decode(FAST_EXAMPLES[1].0).unwrap()).unwrap();
+
+        for backend in Backend::ALL.iter().copied().filter(|b| b.is_supported()) {
+            for rounds in &[&[1000][..], &[1000, 1000], &[10, 1000], &[1000, 10]] {
+                let params: Vec<_> = rounds
+                    .iter()
+                    .enumerate()
+                    .map(|(i, rounds)| Blake2FParams {
+                        rounds: *rounds,
+                        block: [i as u64; 16],
+                        ..base
+                    })
+                    .collect();
+
+                let mut results = [[0; 64]; LANES];
+                let mixed = crate::backend::tests::rounds_mixed(|| {
+                    results = compress_lanes_with(backend, &params);
+                });
+
+                // Mixing a round of all lanes at once counts as one round, so lanes which are
+                // done or unused cost nothing.
+                let expected = match backend {
+                    Backend::Avx2 => *rounds.iter().max().unwrap(),
+                    _ => rounds.iter().sum(),
+                };
+                assert_eq!(mixed, expected, "{} backend, rounds {:?}", backend, rounds);
+                for (result, p) in results.iter().zip(params.iter()) {
+                    assert_eq!(*result, p.compress());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_parameters_error() {
+        for inp in ERROR_EXAMPLES {
+            let input_bytes = hex::decode(inp).unwrap();
+
+            if decode_parameters(&input_bytes).is_ok() {
+                panic!("expected Result::Err but got Result::Ok");
+            }
+        }
+    }
+
+    #[test]
+    fn test_compress_with_checks() {
+        let input_bytes = hex::decode(SLOW_EXAMPLES[0].0).unwrap();
+        let Blake2FParams {
+            rounds,
+            state: starting_state,
+            block,
+            offset_counters,
+            final_block_flag,
+        } = decode_parameters(&input_bytes).unwrap();
+
+        let mut checks = Vec::new();
+        let result = compress_with_checks(
+            rounds,
+            &State(starting_state),
+            &Block(block),
+            Counter::from(offset_counters),
+            final_block_flag,
+            300_000,
+            |rounds_done| {
+                checks.push(rounds_done);
+                Ok::<(), ()>(())
+            },
+        )
+        .unwrap();
+
+        assert_eq!(hex::encode(&result.to_bytes()[..]), SLOW_EXAMPLES[0].1);
+        assert_eq!(checks, (1..7).map(|i| i * 300_000).collect::<Vec<_>>());
+
+        let mut checks = 0;
+        let result = compress_with_checks(
+            u32::MAX as usize,
+            &State(starting_state),
+            &Block(block),
+            Counter::from(offset_counters),
+            final_block_flag,
+            1000,
+            |rounds_done| {
+                checks += 1;
+                if rounds_done >= 5000 {
+                    Err(rounds_done)
+                } else {
+                    Ok(())
+                }
+            },
+        );
+
+        assert_eq!(result, Err(5000));
+        assert_eq!(checks, 5);
+    }
+
+    #[test]
+    fn test_encode_parameters_roundtrip() {
+        for (inp, _) in FAST_EXAMPLES.iter().chain(SLOW_EXAMPLES.iter()) {
+            let input_bytes = hex::decode(inp).unwrap();
+            let params = decode_parameters(&input_bytes).unwrap();
+
+            assert_eq!(params.encode().unwrap()[..], input_bytes[..]);
+        }
+    }
+
+    #[test]
+    fn test_encode_parameters_error() {
+        let state = [0u64; 8];
+        let block = [0u64; 16];
+        let offset_counters = [0u64; 2];
+
+        assert_eq!(
+            encode_parameters(1 << 32, &state, &block, &offset_counters, true),
+            Err(CompressError::InvalidRounds { rounds: 1 << 32 }),
+        );
+        assert_eq!(
+            encode_parameters(12, &state[..7], &block, &offset_counters, true),
+            Err(CompressError::InvalidStartingStateLength { length: 7 }),
+        );
+        assert_eq!(
+            encode_parameters(12, &state, &block[..15], &offset_counters, true),
+            Err(CompressError::InvalidBlockLength { length: 15 }),
+        );
+        assert_eq!(
+            encode_parameters(12, &state, &block, &offset_counters[..1], true),
+            Err(CompressError::InvalidOffsetCountersLength { length: 1 }),
+        );
+    }
+
+    #[test]
+    fn test_decode_parameters_error_fields() {
+        let input_bytes = hex::decode(FAST_EXAMPLES[0].0).unwrap();
+
+        assert_eq!(
+            decode_parameters(&input_bytes[..212]),
+            Err(CompressError::InvalidInputLength { length: 212 }),
+        );
+
+        let mut bad_flag = input_bytes;
+        bad_flag[212] = 2;
+
+        assert_eq!(
+            decode_parameters(&bad_flag),
+            Err(CompressError::InvalidFinalBlockFlag {
+                value: 2,
+                offset: 212,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_compress_blocks_offset() {
+        let blocks = [7u8; 256];
+        let offset = u64::MAX as u128 - 100;
+
+        // The offset counters carry into the high word after the first block.
+        let expected = F(
+            12,
+            &eight_words(&F(12, &IV, &[0x0707070707070707; 16], &[27, 1], false)),
+            &[0x0707070707070707; 16],
+            &[77, 1],
+            true,
+        );
+
+        assert_eq!(
+            compress_blocks(12, &IV, &blocks, offset, 50).unwrap(),
+            expected,
+        );
+    }
+
+    #[test]
+    fn test_compress_lanes() {
+        let base = decode_parameters(&hex::decode(FAST_EXAMPLES[1].0).unwrap()).unwrap();
+        let params: Vec<_> = [12, 0, 300, 1, 7]
+            .iter()
+            .enumerate()
+            .map(|(i, rounds)| Blake2FParams {
+                rounds: *rounds,
+                block: [i as u64; 16],
+                final_block_flag: i % 2 == 0,
+                ..base
+            })
+            .collect();
+
+        for n in 0..params.len() {
+            let results = compress_lanes(&params[..n]);
+
+            for (i, result) in results.iter().enumerate() {
+                if i < n.min(LANES) {
+                    assert_eq!(*result, params[i].compress(), "lane {} of {}", i, n);
+                } else {
+                    assert_eq!(*result, [0; 64], "lane {} of {}", i, n);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_compress_blocks_error() {
+        for length in &[0, 127, 129] {
+            assert_eq!(
+                compress_blocks(12, &IV, &vec![0; *length], 0, 0),
+                Err(CompressError::InvalidBlocksLength { length: *length }),
+            );
+        }
+        assert_eq!(
+            compress_blocks(12, &IV, &[0; 128], 0, 129),
+            Err(CompressError::InvalidLastBlockLength { length: 129 }),
+        );
+        assert_eq!(
+            compress_blocks(12, &IV[..7], &[0; 128], 0, 0),
+            Err(CompressError::InvalidStartingStateLength { length: 7 }),
+        );
+    }
+}
+
+#[cfg(test)]
+mod bench {
+    extern crate test;
+
+    use super::*;
+
+    use crate::backend::Backend;
+    use test::Bencher;
+
+    /// The first test vector from EIP-152, with the number of rounds replaced in each benchmark.
+    const INPUT: &str = "0000000048c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001";
+
+    fn rounds_benchmark(rounds: usize, bencher: &mut Bencher) {
+        let input_bytes = hex::decode(INPUT).unwrap();
+
+        let Blake2FParams {
+            state: starting_state,
+            block,
+            offset_counters,
+            final_block_flag,
+            ..
+        } = decode_parameters(&input_bytes).unwrap();
+
+        bencher.iter(|| {
+            F(
+                rounds,
+                &starting_state,
+                &block,
+                &offset_counters,
+                final_block_flag,
+            )
+            .to_vec()
+        });
+    }
+
+    #[bench]
+    fn bench_100_000_rounds(bencher: &mut Bencher) {
+        rounds_benchmark(100_000, bencher);
+    }
+
+    #[bench]
+    fn bench_2_000_000_rounds(bencher: &mut Bencher) {
+        rounds_benchmark(2_000_000, bencher);
+    }
+
+    #[bench]
+    fn bench_8_000_000_rounds(bencher: &mut Bencher) {
+        rounds_benchmark(8_000_000, bencher);
+    }
+
+    /// Benchmark the rounds of mixing with the scalar backend, as used by portable builds.
+    fn scalar_rounds_benchmark(rounds: usize, bencher: &mut Bencher) {
+        let params = decode_parameters(&hex::decode(INPUT).unwrap()).unwrap();
+        let v = working_vector(&params.state, &params.offset_counters, true, false);
+
+        bencher.iter(|| {
+            let mut v = v;
+            Backend::Scalar.mix(&mut v, &params.block, 0..rounds);
+            v
+        });
+    }
+
+    #[bench]
+    fn bench_scalar_100_000_rounds(bencher: &mut Bencher) {
+        scalar_rounds_benchmark(100_000, bencher);
+    }
+
+    #[bench]
+    fn bench_scalar_2_000_000_rounds(bencher: &mut Bencher) {
+        scalar_rounds_benchmark(2_000_000, bencher);
+    }
+
+    #[bench]
+    fn bench_scalar_8_000_000_rounds(bencher: &mut Bencher) {
+        scalar_rounds_benchmark(8_000_000, bencher);
+    }
+}
@@ -0,0 +1,140 @@
+use core::fmt;
+
+/// The number of bytes in the tightly packed input of the blake2 F precompile.
+pub const INPUT_LENGTH: usize = 213;
+
+/// The offset of the final block indicator flag in the tightly packed input of the blake2 F
+/// precompile.
+pub const FINAL_BLOCK_FLAG_OFFSET: usize = 212;
+
+/// An error in the arguments to the compression function F, either decoded from the tightly
+/// packed precompile input or given directly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompressError {
+    /// The tightly packed input does not have length 213.
+    InvalidInputLength { length: usize },
+    /// The final block indicator flag at offset 212 of the tightly packed input is neither 0
+    /// nor 1.
+    InvalidFinalBlockFlag { value: u8, offset: usize },
+    /// The starting state vector does not have 8 words.
+    InvalidStartingStateLength { length: usize },
+    /// The block vector does not have 16 words.
+    InvalidBlockLength { length: usize },
+    /// The offset counters vector does not have 2 words.
+    InvalidOffsetCountersLength { length: usize },
+    /// The number of rounds does not fit in the 4 bytes of the tightly packed input.
+    InvalidRounds { rounds: usize },
+    /// The blocks given to `compress_blocks` are not a non-zero whole number of 128-byte blocks.
+    InvalidBlocksLength { length: usize },
+    /// The length of the last block given to `compress_blocks` is more than 128 bytes.
+    InvalidLastBlockLength { length: usize },
+}
+
+impl fmt::Display for CompressError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompressError::InvalidInputLength { length } => write!(
+                f,
+                "input length for blake2 F precompile should be exactly {} bytes, got: {}",
+                INPUT_LENGTH, length,
+            ),
+            CompressError::InvalidFinalBlockFlag { value, .. } => {
+                write!(f, "incorrect final block indicator flag, got: {}", value)
+            }
+            CompressError::InvalidStartingStateLength { length } => write!(
+                f,
+                "starting state vector must have length 8, got: {}",
+                length,
+            ),
+            CompressError::InvalidBlockLength { length } => {
+                write!(f, "block vector must have length 16, got: {}", length)
+            }
+            CompressError::InvalidOffsetCountersLength { length } => write!(
+                f,
+                "offset counters vector must have length 2, got: {}",
+                length,
+            ),
+            CompressError::InvalidRounds { rounds } => write!(
+                f,
+                "rounds for blake2 F precompile must be at most {}, got: {}",
+                u32::MAX,
+                rounds,
+            ),
+            CompressError::InvalidBlocksLength { length } => write!(
+                f,
+                "blocks must be a non-zero whole number of 128-byte blocks, got length: {}",
+                length,
+            ),
+            CompressError::InvalidLastBlockLength { length } => {
+                write!(f, "last block length must be at most 128, got: {}", length,)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CompressError {}
+
+/// Check the lengths of the vector arguments to a compression function F, which has a starting
+/// state of 8 words, a block of 16 words and 2 offset counter words for both blake2b and blake2s.
+pub fn check_vector_lengths(
+    starting_state_length: usize,
+    block_length: usize,
+    offset_counters_length: usize,
+) -> Result<(), CompressError> {
+    if starting_state_length != 8 {
+        return Err(CompressError::InvalidStartingStateLength {
+            length: starting_state_length,
+        });
+    }
+    if block_length != 16 {
+        return Err(CompressError::InvalidBlockLength {
+            length: block_length,
+        });
+    }
+    if offset_counters_length != 2 {
+        return Err(CompressError::InvalidOffsetCountersLength {
+            length: offset_counters_length,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_vector_lengths() {
+        assert_eq!(check_vector_lengths(8, 16, 2), Ok(()));
+        assert_eq!(
+            check_vector_lengths(7, 16, 2),
+            Err(CompressError::InvalidStartingStateLength { length: 7 }),
+        );
+        assert_eq!(
+            check_vector_lengths(8, 17, 2),
+            Err(CompressError::InvalidBlockLength { length: 17 }),
+        );
+        assert_eq!(
+            check_vector_lengths(8, 16, 0),
+            Err(CompressError::InvalidOffsetCountersLength { length: 0 }),
+        );
+    }
+
+    #[test]
+    fn test_compress_error_messages() {
+        assert_eq!(
+            CompressError::InvalidInputLength { length: 212 }.to_string(),
+            "input length for blake2 F precompile should be exactly 213 bytes, got: 212",
+        );
+        assert_eq!(
+            CompressError::InvalidFinalBlockFlag {
+                value: 2,
+                offset: FINAL_BLOCK_FLAG_OFFSET,
+            }
+            .to_string(),
+            "incorrect final block indicator flag, got: 2",
+        );
+    }
+}
@@ -0,0 +1,27 @@
+//! The blake2b compression function F, as used by the blake2 F precompile from EIP-152 and by the
+//! blake2b hashes built on it.
+//!
+//! The crate is `no_std` and never allocates, so that the same compression code can be run in
+//! embedded and zkVM environments.  With the default `std` feature, the fastest SIMD backend
+//! supported by the CPU is detected at runtime.  Without it, a backend is only used if it is
+//! enabled at compile time, such as with `-C target-feature=+avx2`, and the portable scalar
+//! backend is used otherwise.
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
+#![cfg_attr(test, feature(test))]
+
+#[cfg(target_arch = "x86_64")]
+mod avx2;
+pub mod backend;
+mod blake2b;
+pub mod error;
+pub mod precompile;
+mod scalar;
+#[cfg(target_arch = "x86_64")]
+mod sse41;
+
+pub use crate::blake2b::{
+    compress, compress_blocks, compress_lanes, compress_with_checks, compress_with_last_node,
+    decode_parameters, encode_parameters, Blake2FParams, Block, Counter, State, BLOCKBYTES, IV,
+    SIGMA, SIGMA_LEN,
+};
+pub use crate::error::CompressError;
@@ -0,0 +1,133 @@
+use core::fmt;
+
+use crate::blake2b::decode_parameters;
+use crate::error::CompressError;
+
+/// The gas charged by the blake2 F precompile for each round of mixing.
+///
+/// See here: https://eips.ethereum.org/EIPS/eip-152#gas-costs-and-benchmarks
+pub const GAS_PER_ROUND: u64 = 1;
+
+/// An error from the blake2 F precompile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrecompileError {
+    /// The input is not a valid tightly packed encoding of the compression function parameters.
+    InvalidInput(CompressError),
+    /// The gas limit does not cover the gas required by the number of rounds in the input.
+    OutOfGas { gas_required: u64, gas_limit: u64 },
+}
+
+impl fmt::Display for PrecompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PrecompileError::InvalidInput(err) => write!(f, "{}", err),
+            PrecompileError::OutOfGas {
+                gas_required,
+                gas_limit,
+            } => write!(
+                f,
+                "out of gas for blake2 F precompile, required: {}, limit: {}",
+                gas_required, gas_limit,
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PrecompileError {}
+
+/// Run the blake2 F precompile on the tightly packed encoding in the byte sequence `input`,
+/// returning the gas used along with the output.  The gas required is checked against
+/// `gas_limit` before any rounds of mixing are done, so that an input with up to 2 ** 32 - 1
+/// rounds costs nothing to reject.
+///
+/// See here: https://eips.ethereum.org/EIPS/eip-152#specification
+pub fn blake2f_precompile(
+    input: &[u8],
+    gas_limit: u64,
+) -> Result<(u64, [u8; 64]), PrecompileError> {
+    let params = decode_parameters(input).map_err(PrecompileError::InvalidInput)?;
+
+    let gas_required = params.rounds as u64 * GAS_PER_ROUND;
+    if gas_required > gas_limit {
+        return Err(PrecompileError::OutOfGas {
+            gas_required,
+            gas_limit,
+        });
+    }
+
+    Ok((gas_required, params.compress()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The fifth test vector from EIP-152, with 12 rounds.
+    const INPUT: &str = "0000000c48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001";
+    const OUTPUT: &str = "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923";
+
+    #[test]
+    fn test_precompile_success() {
+        let input = hex::decode(INPUT).unwrap();
+
+        for gas_limit in &[12, 13, u64::MAX] {
+            let (gas_used, output) = blake2f_precompile(&input, *gas_limit).unwrap();
+
+            assert_eq!(gas_used, 12);
+            assert_eq!(hex::encode(&output[..]), OUTPUT);
+        }
+    }
+
+    #[test]
+    fn test_precompile_out_of_gas() {
+        let mut input = hex::decode(INPUT).unwrap();
+
+        assert_eq!(
+            blake2f_precompile(&input, 11),
+            Err(PrecompileError::OutOfGas {
+                gas_required: 12,
+                gas_limit: 11,
+            }),
+        );
+
+        // The maximal number of rounds is rejected without doing any of the work.
+        input[..4].copy_from_slice(&[0xff; 4]);
+
+        assert_eq!(
+            blake2f_precompile(&input, 30_000_000),
+            Err(PrecompileError::OutOfGas {
+                gas_required: u32::MAX as u64,
+                gas_limit: 30_000_000,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_precompile_invalid_input() {
+        let input = hex::decode(INPUT).unwrap();
+        let mut bad_flag = input.clone();
+        bad_flag[212] = 2;
+
+        assert_eq!(
+            blake2f_precompile(&input[..212], u64::MAX),
+            Err(PrecompileError::InvalidInput(
+                CompressError::InvalidInputLength { length: 212 }
+            )),
+        );
+        assert_eq!(
+            blake2f_precompile(&bad_flag, u64::MAX),
+            Err(PrecompileError::InvalidInput(
+                CompressError::InvalidFinalBlockFlag {
+                    value: 2,
+                    offset: 212,
+                }
+            )),
+        );
+        // Invalid input is reported ahead of any shortage of gas.
+        assert!(matches!(
+            blake2f_precompile(&input[..212], 0),
+            Err(PrecompileError::InvalidInput(_)),
+        ));
+    }
+}
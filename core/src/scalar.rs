@@ -0,0 +1,154 @@
+use core::ops::Range;
+
+use crate::blake2b::{SIGMA, SIGMA_LEN};
+
+/// Do rounds `rounds` of mixing of the message block `m` into the working vector `v` with portable
+/// code.  The words of the working vector are held in locals, and the rounds are unrolled over the
+/// 10-round cycle of the message schedule so that each message word is read at a fixed index.
+///
+/// See here: https://tools.ietf.org/html/rfc7693#section-3.2
+pub(crate) fn mix(v: &mut [u64; 16], m: &[u64; 16], rounds: Range<usize>) {
+    #[rustfmt::skip]
+    let [
+        mut v0, mut v1, mut v2, mut v3,
+        mut v4, mut v5, mut v6, mut v7,
+        mut v8, mut v9, mut v10, mut v11,
+        mut v12, mut v13, mut v14, mut v15,
+    ] = *v;
+
+    // The blake2b mixing function G.
+    //
+    // See here: https://tools.ietf.org/html/rfc7693#section-3.1
+    macro_rules! G {
+        ($a:ident, $b:ident, $c:ident, $d:ident, $x:expr, $y:expr) => {
+            $a = $a.wrapping_add($b).wrapping_add($x);
+            $d = ($d ^ $a).rotate_right(32);
+            $c = $c.wrapping_add($d);
+            $b = ($b ^ $c).rotate_right(24);
+            $a = $a.wrapping_add($b).wrapping_add($y);
+            $d = ($d ^ $a).rotate_right(16);
+            $c = $c.wrapping_add($d);
+            $b = ($b ^ $c).rotate_right(63);
+        };
+    }
+
+    // Round `r` of mixing, for a constant `r` less than 10.
+    macro_rules! round {
+        ($r:expr) => {{
+            const S: [usize; 16] = SIGMA[$r];
+
+            G!(v0, v4, v8, v12, m[S[0]], m[S[1]]);
+            G!(v1, v5, v9, v13, m[S[2]], m[S[3]]);
+            G!(v2, v6, v10, v14, m[S[4]], m[S[5]]);
+            G!(v3, v7, v11, v15, m[S[6]], m[S[7]]);
+
+            G!(v0, v5, v10, v15, m[S[8]], m[S[9]]);
+            G!(v1, v6, v11, v12, m[S[10]], m[S[11]]);
+            G!(v2, v7, v8, v13, m[S[12]], m[S[13]]);
+            G!(v3, v4, v9, v14, m[S[14]], m[S[15]]);
+        }};
+    }
+
+    // Round `r` of mixing, for any `r`.
+    macro_rules! any_round {
+        ($r:expr) => {
+            match $r % SIGMA_LEN {
+                0 => round!(0),
+                1 => round!(1),
+                2 => round!(2),
+                3 => round!(3),
+                4 => round!(4),
+                5 => round!(5),
+                6 => round!(6),
+                7 => round!(7),
+                8 => round!(8),
+                _ => round!(9),
+            }
+        };
+    }
+
+    let Range { start: mut r, end } = rounds;
+
+    // Run single rounds up to the start of a cycle of the message schedule, then whole cycles,
+    // then the rounds that are left.
+    while r < end && r % SIGMA_LEN != 0 {
+        any_round!(r);
+        r += 1;
+    }
+    while end.saturating_sub(r) >= SIGMA_LEN {
+        round!(0);
+        round!(1);
+        round!(2);
+        round!(3);
+        round!(4);
+        round!(5);
+        round!(6);
+        round!(7);
+        round!(8);
+        round!(9);
+        r += SIGMA_LEN;
+    }
+    while r < end {
+        any_round!(r);
+        r += 1;
+    }
+
+    #[rustfmt::skip]
+    let mixed = [
+        v0, v1, v2, v3,
+        v4, v5, v6, v7,
+        v8, v9, v10, v11,
+        v12, v13, v14, v15,
+    ];
+    *v = mixed;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::blake2b::{chain_value, decode_parameters, working_vector};
+
+    /// The fifth test vector from EIP-152, with 12 rounds.
+    const INPUT: &str = "0000000c48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001";
+    const OUTPUT: &str = "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923";
+
+    #[test]
+    fn test_mix_known_output() {
+        let params = decode_parameters(&hex::decode(INPUT).unwrap()).unwrap();
+        let mut v = working_vector(&params.state, &params.offset_counters, true, false);
+        mix(&mut v, &params.block, 0..params.rounds);
+
+        let output: Vec<u8> = chain_value(&params.state, &v)
+            .iter()
+            .flat_map(|word| word.to_le_bytes().to_vec())
+            .collect();
+
+        assert_eq!(hex::encode(output), OUTPUT);
+    }
+
+    #[test]
+    fn test_mix_in_parts() {
+        let params = decode_parameters(&hex::decode(INPUT).unwrap()).unwrap();
+        let start = working_vector(&params.state, &params.offset_counters, true, false);
+
+        let mut expected = start;
+        mix(&mut expected, &params.block, 0..47);
+
+        // Parts which start and end both at and between cycles of the message schedule.
+        for split in &[
+            [0, 0, 47],
+            [3, 10, 47],
+            [7, 31, 47],
+            [10, 20, 47],
+            [0, 46, 47],
+        ] {
+            let mut v = start;
+            mix(&mut v, &params.block, 0..split[0]);
+            mix(&mut v, &params.block, split[0]..split[1]);
+            mix(&mut v, &params.block, split[1]..split[2]);
+
+            assert_eq!(v, expected, "split at {:?}", split);
+        }
+    }
+}
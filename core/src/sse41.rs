@@ -0,0 +1,121 @@
+use core::arch::x86_64::*;
+use core::ops::Range;
+
+use crate::blake2b::{SIGMA, SIGMA_LEN};
+
+// Each row of the working vector is held in two 128-bit registers, with the low register holding
+// the words of the first two columns and the high register those of the last two columns.
+
+#[inline(always)]
+unsafe fn words(m: &[u64; 16], lo: usize, hi: usize) -> __m128i {
+    _mm_set_epi64x(m[hi] as i64, m[lo] as i64)
+}
+
+/// Rotate the bits in each 64-bit word of `x` to the right by 32 bits.
+#[inline(always)]
+unsafe fn rotate_32(x: __m128i) -> __m128i {
+    _mm_shuffle_epi32(x, 0b10_11_00_01)
+}
+
+/// Rotate the bits in each 64-bit word of `x` to the right by 24 or 16 bits with the byte shuffle
+/// `rotation`.
+#[inline(always)]
+unsafe fn rotate_bytes(x: __m128i, rotation: __m128i) -> __m128i {
+    _mm_shuffle_epi8(x, rotation)
+}
+
+/// Rotate the bits in each 64-bit word of `x` to the right by 63 bits.
+#[inline(always)]
+unsafe fn rotate_63(x: __m128i) -> __m128i {
+    _mm_xor_si128(_mm_srli_epi64(x, 63), _mm_add_epi64(x, x))
+}
+
+/// The blake2b mixing function G on two columns or diagonals at once.
+///
+/// See here: https://tools.ietf.org/html/rfc7693#section-3.1
+#[allow(non_snake_case)]
+#[allow(clippy::too_many_arguments)]
+#[inline(always)]
+unsafe fn G(
+    a: &mut __m128i,
+    b: &mut __m128i,
+    c: &mut __m128i,
+    d: &mut __m128i,
+    x: __m128i,
+    y: __m128i,
+    rotate_24: __m128i,
+    rotate_16: __m128i,
+) {
+    *a = _mm_add_epi64(_mm_add_epi64(*a, *b), x);
+    *d = rotate_32(_mm_xor_si128(*d, *a));
+    *c = _mm_add_epi64(*c, *d);
+    *b = rotate_bytes(_mm_xor_si128(*b, *c), rotate_24);
+    *a = _mm_add_epi64(_mm_add_epi64(*a, *b), y);
+    *d = rotate_bytes(_mm_xor_si128(*d, *a), rotate_16);
+    *c = _mm_add_epi64(*c, *d);
+    *b = rotate_63(_mm_xor_si128(*b, *c));
+}
+
+/// Do rounds `rounds` of mixing of the message block `m` into the working vector `v` with SSE4.1
+/// instructions.
+///
+/// The caller must check that the CPU supports SSE4.1.
+#[target_feature(enable = "sse4.1")]
+pub(crate) unsafe fn mix(v: &mut [u64; 16], m: &[u64; 16], rounds: Range<usize>) {
+    let rows = v.as_mut_ptr() as *mut __m128i;
+    let (mut a_lo, mut a_hi) = (_mm_loadu_si128(rows), _mm_loadu_si128(rows.add(1)));
+    let (mut b_lo, mut b_hi) = (_mm_loadu_si128(rows.add(2)), _mm_loadu_si128(rows.add(3)));
+    let (mut c_lo, mut c_hi) = (_mm_loadu_si128(rows.add(4)), _mm_loadu_si128(rows.add(5)));
+    let (mut d_lo, mut d_hi) = (_mm_loadu_si128(rows.add(6)), _mm_loadu_si128(rows.add(7)));
+
+    let rotate_24 = _mm_setr_epi8(3, 4, 5, 6, 7, 0, 1, 2, 11, 12, 13, 14, 15, 8, 9, 10);
+    let rotate_16 = _mm_setr_epi8(2, 3, 4, 5, 6, 7, 0, 1, 10, 11, 12, 13, 14, 15, 8, 9);
+
+    for r in rounds {
+        let s = &SIGMA[r % SIGMA_LEN];
+
+        // Mix the columns.
+        #[rustfmt::skip]
+        G(&mut a_lo, &mut b_lo, &mut c_lo, &mut d_lo,
+          words(m, s[0], s[2]), words(m, s[1], s[3]), rotate_24, rotate_16);
+        #[rustfmt::skip]
+        G(&mut a_hi, &mut b_hi, &mut c_hi, &mut d_hi,
+          words(m, s[4], s[6]), words(m, s[5], s[7]), rotate_24, rotate_16);
+
+        // Rotate the words of the last three rows to the left by one, two and three columns so
+        // that the diagonals line up as columns.
+        let b_1_2 = _mm_alignr_epi8(b_hi, b_lo, 8);
+        b_hi = _mm_alignr_epi8(b_lo, b_hi, 8);
+        b_lo = b_1_2;
+        core::mem::swap(&mut c_lo, &mut c_hi);
+        let d_3_0 = _mm_alignr_epi8(d_lo, d_hi, 8);
+        d_hi = _mm_alignr_epi8(d_hi, d_lo, 8);
+        d_lo = d_3_0;
+
+        // Mix the diagonals.
+        #[rustfmt::skip]
+        G(&mut a_lo, &mut b_lo, &mut c_lo, &mut d_lo,
+          words(m, s[8], s[10]), words(m, s[9], s[11]), rotate_24, rotate_16);
+        #[rustfmt::skip]
+        G(&mut a_hi, &mut b_hi, &mut c_hi, &mut d_hi,
+          words(m, s[12], s[14]), words(m, s[13], s[15]), rotate_24, rotate_16);
+
+        // Rotate the words of the last three rows back into place.
+        let b_0_1 = _mm_alignr_epi8(b_lo, b_hi, 8);
+        b_hi = _mm_alignr_epi8(b_hi, b_lo, 8);
+        b_lo = b_0_1;
+        core::mem::swap(&mut c_lo, &mut c_hi);
+        let d_0_1 = _mm_alignr_epi8(d_hi, d_lo, 8);
+        d_hi = _mm_alignr_epi8(d_lo, d_hi, 8);
+        d_lo = d_0_1;
+    }
+
+    _mm_storeu_si128(rows, a_lo);
+    _mm_storeu_si128(rows.add(1), a_hi);
+    _mm_storeu_si128(rows.add(2), b_lo);
+    _mm_storeu_si128(rows.add(3), b_hi);
+    _mm_storeu_si128(rows.add(4), c_lo);
+    _mm_storeu_si128(rows.add(5), c_hi);
+    _mm_storeu_si128(rows.add(6), d_lo);
+    _mm_storeu_si128(rows.add(7), d_hi);
+}
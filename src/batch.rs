@@ -0,0 +1,190 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use blake2b_core::backend::LANES;
+use blake2b_core::{compress_lanes, decode_parameters, Blake2FParams, CompressError};
+
+/// Decode the tightly packed blake2 precompile input `input` and run the compression function F on
+/// it.
+pub fn decode_and_compress(input: &[u8]) -> Result<[u8; 64], CompressError> {
+    Ok(decode_parameters(input)?.compress())
+}
+
+/// Run the compression function F on each of the independent sets of parameters in `params`,
+/// returning the results in order.  Instances with similar numbers of rounds are run together in
+/// groups of `LANES`, so that little mixing is wasted on lanes which are already done.
+pub fn compress_many(params: &[Blake2FParams]) -> Vec<[u8; 64]> {
+    let mut order: Vec<usize> = (0..params.len()).collect();
+    order.sort_by_key(|i| params[*i].rounds);
+
+    let mut results = vec![[0u8; 64]; params.len()];
+    for group in order.chunks(LANES) {
+        let group_params: Vec<_> = group.iter().map(|i| params[*i]).collect();
+        for (i, result) in group.iter().zip(compress_lanes(&group_params).iter()) {
+            results[*i] = *result;
+        }
+    }
+
+    results
+}
+
+/// Run the compression function F on each of the independent sets of parameters in `params`
+/// across `threads` worker threads, returning the results in order.  The instances are sorted by
+/// their number of rounds and split into groups of `LANES`, which are mixed together in SIMD lanes
+/// where the CPU supports it.  Workers take the next remaining group, with the most rounds first,
+/// as they finish each one, so that a few groups with many rounds do not hold up the rest.
+pub fn compress_batch(params: &[Blake2FParams], threads: usize) -> Vec<[u8; 64]> {
+    let threads = threads.clamp(1, params.len().div_ceil(LANES).max(1));
+    if threads == 1 {
+        return compress_many(params);
+    }
+
+    let mut order: Vec<usize> = (0..params.len()).collect();
+    order.sort_by_key(|i| std::cmp::Reverse(params[*i].rounds));
+    let groups: Vec<&[usize]> = order.chunks(LANES).collect();
+
+    let next = AtomicUsize::new(0);
+    let worker = || {
+        let mut results = Vec::new();
+        loop {
+            let g = next.fetch_add(1, Ordering::Relaxed);
+            if g >= groups.len() {
+                return results;
+            }
+            let group_params: Vec<_> = groups[g].iter().map(|i| params[*i]).collect();
+            results.extend(groups[g].iter().copied().zip(compress_many(&group_params)));
+        }
+    };
+
+    let mut results: Vec<_> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads).map(|_| scope.spawn(worker)).collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    });
+    results.sort_unstable_by_key(|(i, _)| *i);
+
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Run `decode_and_compress` on each of the independent `inputs` across `threads` worker threads,
+/// returning the results in input order.  The valid inputs are compressed together by
+/// `compress_batch`.
+pub fn decode_and_compress_batch<I: AsRef<[u8]>>(
+    inputs: &[I],
+    threads: usize,
+) -> Vec<Result<[u8; 64], CompressError>> {
+    let decoded: Vec<_> = inputs
+        .iter()
+        .map(|input| decode_parameters(input.as_ref()))
+        .collect();
+    let params: Vec<_> = decoded.iter().filter_map(|d| d.ok()).collect();
+
+    let mut outputs = compress_batch(&params, threads).into_iter();
+    decoded
+        .into_iter()
+        .map(|d| d.map(|_| outputs.next().unwrap()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The fifth test vector from EIP-152, with 12 rounds.
+    pub(super) const INPUT: &str = "0000000c48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001";
+
+    #[test]
+    fn test_compress_many() {
+        let base = decode_parameters(&hex::decode(INPUT).unwrap()).unwrap();
+        let params: Vec<_> = [12, 0, 1, 12, 300, 25, 7, 7, 7, 1000, 2]
+            .iter()
+            .enumerate()
+            .map(|(i, rounds)| Blake2FParams {
+                rounds: *rounds,
+                block: [i as u64; 16],
+                final_block_flag: i % 2 == 0,
+                ..base
+            })
+            .collect();
+
+        for n in 0..params.len() {
+            let expected: Vec<_> = params[..n].iter().map(|p| p.compress()).collect();
+
+            assert_eq!(compress_many(&params[..n]), expected);
+        }
+    }
+
+    #[test]
+    fn test_batch_preserves_input_order() {
+        let base = hex::decode(INPUT).unwrap();
+        let inputs: Vec<Vec<u8>> = (0..100u32)
+            .map(|i| {
+                let mut input = base.clone();
+                input[..4].copy_from_slice(&(i * 100).to_be_bytes());
+                if i % 7 == 3 {
+                    input[212] = 2;
+                }
+                input
+            })
+            .collect();
+        let expected: Vec<_> = inputs
+            .iter()
+            .map(|input| decode_and_compress(input))
+            .collect();
+
+        for threads in &[0, 1, 2, 8, 1000] {
+            assert_eq!(decode_and_compress_batch(&inputs, *threads), expected);
+        }
+        assert_eq!(
+            expected[3],
+            Err(CompressError::InvalidFinalBlockFlag {
+                value: 2,
+                offset: 212,
+            }),
+        );
+    }
+
+    #[test]
+    fn test_batch_empty() {
+        let inputs: [&[u8]; 0] = [];
+
+        assert!(decode_and_compress_batch(&inputs, 4).is_empty());
+    }
+}
+
+#[cfg(test)]
+mod bench {
+    extern crate test;
+
+    use super::*;
+
+    use test::Bencher;
+
+    fn batch_params() -> Vec<Blake2FParams> {
+        let input = hex::decode(tests::INPUT).unwrap();
+        let base = decode_parameters(&input).unwrap();
+
+        (0..64u64)
+            .map(|i| Blake2FParams {
+                rounds: 10_000,
+                block: [i; 16],
+                ..base
+            })
+            .collect()
+    }
+
+    #[bench]
+    fn bench_64_instances_sequentially(bencher: &mut Bencher) {
+        let params = batch_params();
+
+        bencher.iter(|| params.iter().map(|p| p.compress()).collect::<Vec<_>>());
+    }
+
+    #[bench]
+    fn bench_64_instances_in_lanes(bencher: &mut Bencher) {
+        let params = batch_params();
+
+        bencher.iter(|| compress_batch(&params, 1));
+    }
+}
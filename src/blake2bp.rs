@@ -0,0 +1,233 @@
+use crate::hasher::{Blake2b, BLOCKBYTES, KEYBYTES, OUTBYTES, ROUNDS};
+use crate::params::Blake2bParams;
+
+/// The number of leaves hashed in parallel by BLAKE2bp.
+pub const PARALLELISM_DEGREE: usize = 4;
+
+/// The number of bytes in a stripe of message blocks, one for each leaf.
+const STRIPEBYTES: usize = PARALLELISM_DEGREE * BLOCKBYTES;
+
+/// The minimum number of bytes in a single update for which the leaves are hashed on separate
+/// threads.  Below this, spawning threads costs more than it saves.
+const PARALLEL_MIN_BYTES: usize = 1 << 18;
+
+/// A streaming BLAKE2bp hasher.  Message blocks are distributed across four BLAKE2b leaves with
+/// fanout 4 and depth 2, so that block `i` is hashed by leaf `i % 4`.  The 64-byte leaf outputs
+/// are then hashed by a root node to give the digest.
+///
+/// Large updates hash each leaf on its own thread.  The digest does not depend on how the message
+/// is split into updates.
+///
+/// See here: https://blake2.net/blake2.pdf (section 2.9)
+#[derive(Clone)]
+pub struct Blake2bp {
+    leaves: [Blake2b; PARALLELISM_DEGREE],
+    root: Blake2b,
+    buf: [u8; STRIPEBYTES],
+    buf_len: usize,
+    digest_size: usize,
+}
+
+impl Blake2bp {
+    /// Create a hasher producing digests of the full 64 bytes.
+    pub fn new() -> Self {
+        Self::with_digest_size(OUTBYTES).unwrap()
+    }
+
+    /// Create a hasher producing digests of `digest_size` bytes, which must be between 1 and 64.
+    pub fn with_digest_size(digest_size: usize) -> Result<Self, String> {
+        Self::init(digest_size, &[])
+    }
+
+    /// Create a hasher for keyed hashing with a `key` of between 1 and 64 bytes, producing digests
+    /// of `digest_size` bytes.  Each leaf is keyed with `key`.
+    pub fn new_keyed(key: &[u8], digest_size: usize) -> Result<Self, String> {
+        if key.is_empty() || key.len() > KEYBYTES {
+            return Err(format!(
+                "key length must be between 1 and {}, got: {}",
+                KEYBYTES,
+                key.len(),
+            ));
+        }
+
+        Self::init(digest_size, key)
+    }
+
+    fn init(digest_size: usize, key: &[u8]) -> Result<Self, String> {
+        let params = Blake2bParams::new()
+            .digest_length(digest_size)
+            .key_length(key.len())
+            .fanout(PARALLELISM_DEGREE)
+            .depth(2)
+            .inner_length(OUTBYTES);
+
+        let leaf = |i: usize| {
+            let leaf_params = params
+                .node_offset(i as u64)
+                .last_node(i == PARALLELISM_DEGREE - 1);
+            Blake2b::with_params(&leaf_params, key)
+        };
+        let leaves = [leaf(0)?, leaf(1)?, leaf(2)?, leaf(3)?];
+
+        // The root node has the key length in its parameter block, but only the leaves are fed
+        // the key block.
+        let root_params = params.node_depth(1).last_node(true);
+        let root = Blake2b::with_params_without_key(&root_params, ROUNDS)?;
+
+        Ok(Self {
+            leaves,
+            root,
+            buf: [0; STRIPEBYTES],
+            buf_len: 0,
+            digest_size,
+        })
+    }
+
+    /// The number of bytes in the digest produced by this hasher.
+    pub fn digest_size(&self) -> usize {
+        self.digest_size
+    }
+
+    /// Feed the bytes in `data` into the hasher.
+    pub fn update(&mut self, mut data: &[u8]) {
+        // Complete any buffered stripe first.
+        if self.buf_len > 0 {
+            let take = (STRIPEBYTES - self.buf_len).min(data.len());
+            self.buf[self.buf_len..self.buf_len + take].copy_from_slice(&data[..take]);
+            self.buf_len += take;
+            data = &data[take..];
+
+            if self.buf_len < STRIPEBYTES {
+                return;
+            }
+
+            let buf = self.buf;
+            for (i, leaf) in self.leaves.iter_mut().enumerate() {
+                leaf.update(&buf[i * BLOCKBYTES..(i + 1) * BLOCKBYTES]);
+            }
+            self.buf_len = 0;
+        }
+
+        // Hash all whole stripes directly from the input.
+        let stripes_len = data.len() - data.len() % STRIPEBYTES;
+        let (stripes, rest) = data.split_at(stripes_len);
+
+        if stripes.len() >= PARALLEL_MIN_BYTES {
+            std::thread::scope(|scope| {
+                for (i, leaf) in self.leaves.iter_mut().enumerate() {
+                    scope.spawn(move || update_leaf(leaf, i, stripes));
+                }
+            });
+        } else {
+            for (i, leaf) in self.leaves.iter_mut().enumerate() {
+                update_leaf(leaf, i, stripes);
+            }
+        }
+
+        self.buf[..rest.len()].copy_from_slice(rest);
+        self.buf_len = rest.len();
+    }
+
+    /// Return the digest of all bytes fed into the hasher so far.  The hasher itself is left
+    /// unchanged and may continue to be updated.
+    pub fn finalize(&self) -> Vec<u8> {
+        let mut root = self.root.clone();
+
+        for (i, leaf) in self.leaves.iter().enumerate() {
+            let start = (i * BLOCKBYTES).min(self.buf_len);
+            let end = ((i + 1) * BLOCKBYTES).min(self.buf_len);
+
+            let mut leaf = leaf.clone();
+            leaf.update(&self.buf[start..end]);
+            root.update(&leaf.finalize_full());
+        }
+
+        root.finalize()
+    }
+}
+
+impl Default for Blake2bp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Feed leaf `i` its block from each stripe in `stripes`.
+fn update_leaf(leaf: &mut Blake2b, i: usize, stripes: &[u8]) {
+    for stripe in stripes.chunks_exact(STRIPEBYTES) {
+        leaf.update(&stripe[i * BLOCKBYTES..(i + 1) * BLOCKBYTES]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Known digests computed by composing leaf and root hashes with Python's `hashlib.blake2b`
+    /// tree parameters.
+    const EXAMPLES: &[(usize, &str)] = &[
+        (
+            0,
+            "b5ef811a8038f70b628fa8b294daae7492b1ebe343a80eaabbf1f6ae664dd67b9d90b0120791eab81dc96985f28849f6a305186a85501b405114bfa678df9380",
+        ),
+        (
+            3,
+            "8cf933a2d361a3e6a136dbe4a01e7903797ad6ce766e2b91b9b4a4035127d65f4be86550119418e22da00fd06bf2b27596b37f06be0a154aaf7eca54c4520b97",
+        ),
+        (
+            1000,
+            "1ce5b8d6f6fcc89fcb6ed29f12796cc210a03f4763e528cb2c0e1b4b1255d6ae86c79332529f6368d0bcfe9d316a5f999a53af47a8f0ec4412ce19156bbafd04",
+        ),
+    ];
+
+    fn message(len: usize) -> Vec<u8> {
+        (0..len).map(|i| i as u8).collect()
+    }
+
+    #[test]
+    fn test_blake2bp_known_digests() {
+        for (len, expected) in EXAMPLES {
+            let mut hasher = Blake2bp::new();
+            hasher.update(&message(*len));
+
+            assert_eq!(hex::encode(hasher.finalize()), *expected);
+        }
+    }
+
+    #[test]
+    fn test_blake2bp_keyed() {
+        // The first keyed known-answer test from the blake2 reference implementation.
+        let key: Vec<u8> = (0..64).collect();
+        let hasher = Blake2bp::new_keyed(&key, OUTBYTES).unwrap();
+
+        assert_eq!(
+            hex::encode(hasher.finalize()),
+            "9d9461073e4eb640a255357b839f394b838c6ff57c9b686a3f76107c1066728f3c9956bd785cbc3bf79dc2ab578c5a0c063b9d9c405848de1dbe821cd05c940a",
+        );
+    }
+
+    #[test]
+    fn test_blake2bp_incremental_updates() {
+        let data = message(3 * PARALLEL_MIN_BYTES + 1000);
+        let mut expected = Blake2bp::new();
+        expected.update(&data);
+        let expected = expected.finalize();
+
+        for chunk_size in &[1, 127, 128, 511, 512, 513, PARALLEL_MIN_BYTES + 1] {
+            let mut hasher = Blake2bp::new();
+            for chunk in data.chunks(*chunk_size) {
+                hasher.update(chunk);
+            }
+
+            assert_eq!(hasher.finalize(), expected);
+        }
+    }
+
+    #[test]
+    fn test_blake2bp_digest_size_error() {
+        assert!(Blake2bp::with_digest_size(0).is_err());
+        assert!(Blake2bp::with_digest_size(65).is_err());
+        assert!(Blake2bp::new_keyed(&[], 64).is_err());
+        assert!(Blake2bp::new_keyed(&[0; 65], 64).is_err());
+    }
+}
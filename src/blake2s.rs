@@ -0,0 +1,353 @@
+use std::convert::TryInto;
+
+use blake2b_core::error::{check_vector_lengths, CompressError};
+use blake2b_core::{SIGMA, SIGMA_LEN};
+
+use crate::hasher::define_hasher;
+use crate::params::define_params;
+
+const WORDBITS: usize = 32;
+const MASKBITS: u32 = u32::MAX;
+
+const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const ROT1: usize = 16;
+const ROT2: usize = 12;
+const ROT3: usize = 8;
+const ROT4: usize = 7;
+
+/// The number of bytes in a blake2s message block.
+pub const BLOCKBYTES: usize = 64;
+
+/// The maximum number of bytes in a blake2s digest.
+pub const OUTBYTES: usize = 32;
+
+/// The maximum number of bytes in a blake2s key.
+pub const KEYBYTES: usize = 32;
+
+/// The number of bytes in a blake2s parameter block.
+pub const PARAMBYTES: usize = 32;
+
+/// The number of bytes in a blake2s salt.
+pub const SALTBYTES: usize = 8;
+
+/// The number of bytes in a blake2s personalization string.
+pub const PERSONALBYTES: usize = 8;
+
+/// The number of rounds of mixing used by standard blake2s.
+///
+/// See here: https://tools.ietf.org/html/rfc7693#section-3.2
+pub const ROUNDS: usize = 10;
+
+#[inline]
+fn u32_from_le(input: &[u8]) -> u32 {
+    u32::from_le_bytes(input.try_into().unwrap())
+}
+
+#[inline]
+fn eight_words(input: &[u8]) -> [u32; 8] {
+    let mut words = [0u32; 8];
+    for (word, chunk) in words.iter_mut().zip(input.chunks_exact(4)) {
+        *word = u32_from_le(chunk);
+    }
+    words
+}
+
+#[inline]
+fn sixteen_words(input: &[u8]) -> [u32; 16] {
+    let mut words = [0u32; 16];
+    for (word, chunk) in words.iter_mut().zip(input.chunks_exact(4)) {
+        *word = u32_from_le(chunk);
+    }
+    words
+}
+
+/// Rotate bits in the unsigned 32-bit integer `x` to the right by `n` bits.
+///
+/// See here: https://tools.ietf.org/html/rfc7693#section-2.3
+#[inline]
+fn rotate_bits(x: u32, n: usize) -> u32 {
+    (x >> n) ^ (x << (WORDBITS - n))
+}
+
+/// The blake2s mixing function G.
+///
+/// See here: https://tools.ietf.org/html/rfc7693#section-3.1
+#[allow(non_snake_case)]
+#[inline]
+fn G(v: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, x: u32, y: u32) {
+    // Additions are mod 2 ** 32, which we get for free with u32 arithmetic.
+    v[a] = v[a] + v[b] + x;
+    v[d] = rotate_bits(v[d] ^ v[a], ROT1);
+    v[c] += v[d];
+    v[b] = rotate_bits(v[b] ^ v[c], ROT2);
+    v[a] = v[a] + v[b] + y;
+    v[d] = rotate_bits(v[d] ^ v[a], ROT3);
+    v[c] += v[d];
+    v[b] = rotate_bits(v[b] ^ v[c], ROT4);
+}
+
+/// The blake2s compression function F, returning the resulting state vector as words rather than
+/// serialized bytes.  The `last_node_flag` is the second finalization flag f1 used by the tree
+/// hashing modes.
+///
+/// See here: https://tools.ietf.org/html/rfc7693#section-3.2
+pub(crate) fn compress_state(
+    rounds: usize,
+    starting_state: &[u32],
+    block: &[u32],
+    offset_counters: &[u32],
+    final_block_flag: bool,
+    last_node_flag: bool,
+) -> [u32; 8] {
+    let h = starting_state;
+    let m = block;
+    let t = offset_counters;
+
+    let mut v = [
+        h[0],         // 0
+        h[1],         // 1
+        h[2],         // 2
+        h[3],         // 3
+        h[4],         // 4
+        h[5],         // 5
+        h[6],         // 6
+        h[7],         // 7
+        IV[0],        // 8
+        IV[1],        // 9
+        IV[2],        // 10
+        IV[3],        // 11
+        IV[4] ^ t[0], // 12
+        IV[5] ^ t[1], // 13
+        if final_block_flag {
+            MASKBITS ^ IV[6]
+        } else {
+            IV[6]
+        }, // 14
+        if last_node_flag {
+            MASKBITS ^ IV[7]
+        } else {
+            IV[7]
+        }, // 15
+    ];
+
+    for r in 0..rounds {
+        let s = &SIGMA[r % SIGMA_LEN];
+
+        G(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+        G(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+        G(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+        G(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+
+        G(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+        G(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+        G(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+        G(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+
+    [
+        h[0] ^ v[0] ^ v[8],
+        h[1] ^ v[1] ^ v[9],
+        h[2] ^ v[2] ^ v[10],
+        h[3] ^ v[3] ^ v[11],
+        h[4] ^ v[4] ^ v[12],
+        h[5] ^ v[5] ^ v[13],
+        h[6] ^ v[6] ^ v[14],
+        h[7] ^ v[7] ^ v[15],
+    ]
+}
+
+/// The blake2s compression function F.  The vectors must have the same lengths as those passed to
+/// the blake2b compression function, or else an error is returned.
+///
+/// See here: https://tools.ietf.org/html/rfc7693#section-3.2
+#[allow(non_snake_case)]
+pub fn F(
+    rounds: usize,
+    starting_state: &[u32],
+    block: &[u32],
+    offset_counters: &[u32],
+    final_block_flag: bool,
+) -> Result<[u8; 32], CompressError> {
+    check_vector_lengths(starting_state.len(), block.len(), offset_counters.len())?;
+
+    let result_words = compress_state(
+        rounds,
+        starting_state,
+        block,
+        offset_counters,
+        final_block_flag,
+        false,
+    );
+
+    let mut result = [0u8; 32];
+    for (i, word) in result_words.iter().enumerate() {
+        result[i * 4..(i + 1) * 4].copy_from_slice(&word.to_le_bytes());
+    }
+
+    Ok(result)
+}
+
+define_params! {
+    /// A builder for the blake2s parameter block.  This mirrors `Blake2bParams`, except that the
+    /// node offset is limited to 48 bits and the salt and personalization string to 8 bytes each.
+    ///
+    /// See here: https://blake2.net/blake2.pdf (section 2.8)
+    pub struct Blake2sParams {
+        word: u32,
+        iv: IV,
+        parameter_words: eight_words,
+        outbytes: OUTBYTES,
+        keybytes: KEYBYTES,
+        parambytes: PARAMBYTES,
+        saltbytes: SALTBYTES,
+        personalbytes: PERSONALBYTES,
+        node_offset_bytes: 6,
+    }
+}
+
+define_hasher! {
+    /// A streaming blake2s hasher built on the compression function F.  This mirrors the blake2b
+    /// `Blake2b` hasher.
+    ///
+    /// See here: https://tools.ietf.org/html/rfc7693#section-3.3
+    pub struct Blake2s {
+        params: Blake2sParams,
+        word: u32,
+        counter: u64,
+        blockbytes: BLOCKBYTES,
+        outbytes: OUTBYTES,
+        keybytes: KEYBYTES,
+        rounds: ROUNDS,
+    }
+}
+
+impl Blake2s {
+    fn compress(&mut self, final_block_flag: bool) {
+        self.t = self.t.wrapping_add(self.buf_len as u64);
+
+        let block = sixteen_words(&self.buf);
+        let offset_counters = [self.t as u32, (self.t >> 32) as u32];
+
+        self.h = compress_state(
+            self.rounds,
+            &self.h,
+            &block,
+            &offset_counters,
+            final_block_flag,
+            final_block_flag && self.last_node,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Known digests, including that of `b"abc"` given in RFC 7693.
+    ///
+    /// See here: https://tools.ietf.org/html/rfc7693#appendix-B
+    const EXAMPLES: &[(&[u8], &str)] = &[
+        (
+            b"abc",
+            "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982",
+        ),
+        (
+            b"",
+            "69217a3079908094e11121d042354a7c1f55b6482ca1a51e1b250dfd1ed0eef9",
+        ),
+    ];
+
+    #[test]
+    fn test_blake2s_f() {
+        // The single block compression of `b"abc"` from RFC 7693.
+        let h = Blake2sParams::new().starting_state().unwrap();
+        let mut block = [0u8; BLOCKBYTES];
+        block[..3].copy_from_slice(b"abc");
+
+        let result_bytes = F(ROUNDS, &h, &sixteen_words(&block), &[3, 0], true).unwrap();
+
+        assert_eq!(hex::encode(result_bytes), EXAMPLES[0].1);
+    }
+
+    #[test]
+    fn test_blake2s_hasher_known_digests() {
+        for (data, expected) in EXAMPLES {
+            let mut hasher = Blake2s::new();
+            hasher.update(data);
+
+            assert_eq!(hex::encode(hasher.finalize()), *expected);
+        }
+    }
+
+    #[test]
+    fn test_blake2s_hasher_incremental_updates() {
+        let data: Vec<u8> = (0..1024).map(|i| i as u8).collect();
+
+        for chunk_size in &[1, 7, 63, 64, 65, 1024] {
+            let mut hasher = Blake2s::new();
+            for chunk in data.chunks(*chunk_size) {
+                hasher.update(chunk);
+            }
+
+            assert_eq!(
+                hex::encode(hasher.finalize()),
+                "a049455add68f38d48845e25a52ba3100c4d0899178c202aec07364fecacf650",
+            );
+        }
+    }
+
+    #[test]
+    fn test_blake2s_hasher_keyed() {
+        // The first keyed known-answer test from the blake2 reference implementation.
+        let key: Vec<u8> = (0..32).collect();
+        let hasher = Blake2s::new_keyed(&key, OUTBYTES).unwrap();
+
+        assert_eq!(
+            hex::encode(hasher.finalize()),
+            "48a8997da407876b3d79c0d92325ad3b89cbb754d86ab71aee047ad345fd2c49",
+        );
+    }
+
+    #[test]
+    fn test_blake2s_hasher_with_params() {
+        let params = Blake2sParams::new()
+            .digest_length(16)
+            .salt(b"salty")
+            .personal(b"me")
+            .fanout(2)
+            .depth(3)
+            .leaf_length(4096)
+            .node_offset(5)
+            .node_depth(1)
+            .inner_length(32)
+            .last_node(true);
+        let mut hasher = Blake2s::with_params(&params, &[]).unwrap();
+        hasher.update(b"abc");
+
+        assert_eq!(
+            hex::encode(hasher.finalize()),
+            "b04bf8172d603028be5931e8a3bfff6f",
+        );
+    }
+
+    #[test]
+    fn test_blake2s_params_validation_error() {
+        let invalid = [
+            Blake2sParams::new().digest_length(0),
+            Blake2sParams::new().digest_length(33),
+            Blake2sParams::new().key_length(33),
+            Blake2sParams::new().node_offset(1 << 48),
+            Blake2sParams::new().inner_length(33),
+            Blake2sParams::new().salt(&[0; 9]),
+            Blake2sParams::new().personal(&[0; 9]),
+        ];
+
+        for params in invalid.iter() {
+            assert!(params.to_bytes().is_err());
+        }
+        assert!(Blake2s::new_keyed(&[], 32).is_err());
+    }
+}
@@ -0,0 +1,341 @@
+use blake2b_core::{compress, Block, Counter, State, IV};
+
+use crate::hasher::{Blake2b, BLOCKBYTES, KEYBYTES, OUTBYTES, ROUNDS};
+use crate::params::{Blake2bParams, PARAMBYTES, PERSONALBYTES, SALTBYTES};
+
+/// The XOF length recorded in the parameter block when the output length is not known in advance.
+const UNKNOWN_LENGTH: u32 = u32::MAX;
+
+/// The maximum number of output bytes when the output length is not known in advance.  Expansion
+/// blocks are numbered with a 32-bit node offset.
+pub const MAX_UNKNOWN_LENGTH_OUTPUT: u64 = (1 << 32) * OUTBYTES as u64;
+
+/// A streaming BLAKE2Xb hasher, the extendable-output variant of blake2b.
+///
+/// The message is hashed by a root blake2b instance which has the XOF length in its parameter
+/// block.  The 64-byte root hash is then expanded into the output by hashing it once for each
+/// 64-byte output block, with the block number as the node offset.
+///
+/// See here: https://blake2.net/blake2x.pdf
+#[derive(Clone)]
+pub struct Blake2xb {
+    root: Blake2b,
+    xof_length: u32,
+    salt: [u8; SALTBYTES],
+    personal: [u8; PERSONALBYTES],
+}
+
+impl Blake2xb {
+    /// Create a hasher producing `output_length` bytes of output, or an output of unknown length
+    /// if `output_length` is `None`.  A known output length must be between 1 and 2 ** 32 - 2
+    /// bytes.
+    pub fn new(output_length: Option<u32>) -> Result<Self, String> {
+        Self::with_options(output_length, &[], &[], &[])
+    }
+
+    /// Like `new`, except that the hasher is keyed with a `key` of up to 64 bytes and the root
+    /// hash is salted and personalized with a `salt` and `personal` string of up to 16 bytes
+    /// each.
+    pub fn with_options(
+        output_length: Option<u32>,
+        key: &[u8],
+        salt: &[u8],
+        personal: &[u8],
+    ) -> Result<Self, String> {
+        let xof_length = match output_length {
+            None => UNKNOWN_LENGTH,
+            Some(0) | Some(UNKNOWN_LENGTH) => {
+                return Err(format!(
+                    "output length must be between 1 and {}, got: {}",
+                    UNKNOWN_LENGTH - 1,
+                    output_length.unwrap(),
+                ));
+            }
+            Some(n) => n,
+        };
+        if key.len() > KEYBYTES {
+            return Err(format!(
+                "key length must be at most {}, got: {}",
+                KEYBYTES,
+                key.len(),
+            ));
+        }
+
+        // The 64-bit node offset field holds a 32-bit node offset followed by the 32-bit XOF
+        // length.
+        let params = Blake2bParams::new()
+            .key_length(key.len())
+            .node_offset(u64::from(xof_length) << 32)
+            .salt(salt)
+            .personal(personal);
+        let root = Blake2b::with_params(&params, key)?;
+
+        let mut padded_salt = [0; SALTBYTES];
+        padded_salt[..salt.len()].copy_from_slice(salt);
+        let mut padded_personal = [0; PERSONALBYTES];
+        padded_personal[..personal.len()].copy_from_slice(personal);
+
+        Ok(Self {
+            root,
+            xof_length,
+            salt: padded_salt,
+            personal: padded_personal,
+        })
+    }
+
+    /// The number of output bytes, or `None` if the output length is unknown.
+    pub fn output_length(&self) -> Option<u32> {
+        if self.xof_length == UNKNOWN_LENGTH {
+            None
+        } else {
+            Some(self.xof_length)
+        }
+    }
+
+    /// Feed the bytes in `data` into the hasher.
+    pub fn update(&mut self, data: &[u8]) {
+        self.root.update(data);
+    }
+
+    /// Finalize the root hash and return a reader over the output.  The hasher itself is left
+    /// unchanged and may continue to be updated.
+    ///
+    /// If the output length is unknown, every output block is a full 64-byte expansion hash, so
+    /// that the output can be read indefinitely.
+    pub fn finalize_xof(&self) -> Blake2xbReader {
+        let output_length = match self.output_length() {
+            None => MAX_UNKNOWN_LENGTH_OUTPUT,
+            Some(n) => u64::from(n),
+        };
+
+        self.reader(output_length)
+    }
+
+    /// Fill `out` with the output, as done by the reference implementation.  If the output length
+    /// is known, `out` must have exactly that length.  Otherwise, `out` may have any non-zero
+    /// length up to `MAX_UNKNOWN_LENGTH_OUTPUT` and a short last block is hashed with a shorter
+    /// digest length.  In that case, the output may differ from that of `finalize_xof` in its
+    /// last block.
+    pub fn finalize_into(&self, out: &mut [u8]) -> Result<(), String> {
+        self.check_finalize_length(out.len())?;
+        self.reader(out.len() as u64).read(out);
+
+        Ok(())
+    }
+
+    /// Check that `finalize_into` accepts an output buffer of `length` bytes, so that callers can
+    /// check the length before allocating the buffer.
+    pub fn check_finalize_length(&self, length: usize) -> Result<(), String> {
+        let valid = match self.output_length() {
+            None => length != 0 && length as u64 <= MAX_UNKNOWN_LENGTH_OUTPUT,
+            Some(n) => length as u64 == u64::from(n),
+        };
+        if !valid {
+            return Err(format!(
+                "output buffer length does not match output length, got: {}",
+                length,
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn reader(&self, output_length: u64) -> Blake2xbReader {
+        let root_hash = self.root.finalize_full();
+
+        let mut block = [0u8; BLOCKBYTES];
+        block[..OUTBYTES].copy_from_slice(&root_hash);
+
+        Blake2xbReader {
+            root_hash: Block::from_bytes(&block),
+            xof_length: self.xof_length,
+            salt: self.salt,
+            personal: self.personal,
+            output_length,
+            position: 0,
+            output_block: [0; OUTBYTES],
+        }
+    }
+}
+
+/// A reader over the output of a BLAKE2Xb hasher.  Output blocks are computed as they are read.
+#[derive(Clone)]
+pub struct Blake2xbReader {
+    root_hash: Block,
+    xof_length: u32,
+    salt: [u8; SALTBYTES],
+    personal: [u8; PERSONALBYTES],
+    output_length: u64,
+    position: u64,
+    output_block: [u8; OUTBYTES],
+}
+
+impl Blake2xbReader {
+    /// The total number of output bytes which may be read.
+    pub fn output_length(&self) -> u64 {
+        self.output_length
+    }
+
+    /// The number of output bytes read so far.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Fill `out` with the next output bytes, returning the number of bytes written.  Fewer bytes
+    /// than requested are only written once the end of the output is reached.
+    pub fn read(&mut self, out: &mut [u8]) -> usize {
+        let mut written = 0;
+
+        while written < out.len() && self.position < self.output_length() {
+            let offset = (self.position % OUTBYTES as u64) as usize;
+            if offset == 0 {
+                self.compute_output_block();
+            }
+
+            let block_len = (self.output_length() - self.position + offset as u64)
+                .min(OUTBYTES as u64) as usize;
+            let take = (block_len - offset).min(out.len() - written);
+
+            out[written..written + take].copy_from_slice(&self.output_block[offset..offset + take]);
+            written += take;
+            self.position += take as u64;
+        }
+
+        written
+    }
+
+    /// Compute the output block containing the current position by hashing the root hash with
+    /// the block number as the node offset.  Expansion nodes are unkeyed and have a fanout and
+    /// depth of 0, a leaf length and inner length of 64 and a digest length of 64 bytes, or
+    /// fewer for a short last block.
+    fn compute_output_block(&mut self) {
+        let node_offset = self.position / OUTBYTES as u64;
+        let digest_length = (self.output_length() - self.position).min(OUTBYTES as u64);
+
+        let mut parameter_block = [0u8; PARAMBYTES];
+        parameter_block[0] = digest_length as u8;
+        parameter_block[4..8].copy_from_slice(&(OUTBYTES as u32).to_le_bytes());
+        parameter_block[8..12].copy_from_slice(&(node_offset as u32).to_le_bytes());
+        parameter_block[12..16].copy_from_slice(&self.xof_length.to_le_bytes());
+        parameter_block[17] = OUTBYTES as u8;
+        parameter_block[32..48].copy_from_slice(&self.salt);
+        parameter_block[48..64].copy_from_slice(&self.personal);
+
+        let mut h = IV;
+        for (word, parameter_word) in h
+            .iter_mut()
+            .zip(State::from_bytes(&parameter_block).0.iter())
+        {
+            *word ^= parameter_word;
+        }
+
+        self.output_block = compress(
+            ROUNDS,
+            &State(h),
+            &self.root_hash,
+            Counter(OUTBYTES as u128),
+            true,
+        )
+        .to_bytes();
+    }
+}
+
+impl std::io::Read for Blake2xbReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(Blake2xbReader::read(self, buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Outputs computed with Python's `hashlib.blake2b` for the root hash and the reference
+    /// compression function in `tests/reference_impl.py` for the expansion blocks.
+    const EXAMPLES: &[(&[u8], Option<u32>, usize, &str)] = &[
+        (b"", Some(1), 1, "34"),
+        (
+            b"abc",
+            Some(100),
+            100,
+            "e0f82b71c07860b65be612d2633becc46596a6c12a8772b561adec35721b7a5c44a7e075e8a3bc8c4fc8390a197be2085b4aa4385c207f24e46415defc659afd73bacb288080b10849aeea386c60cd3fa04c9bcbfeebaed6e98634d696b9d5bdef0ad2c5",
+        ),
+        (
+            b"abc",
+            None,
+            200,
+            "ae080c1efbcf7f60ed52a04161d02b7ee63bed362534f0661da02c6e40cd208946d066b86b3dff620e57acea9cd72d3056cf6cb0c18341452a17ce2cced67b702669bf0bed358c1b708e97de2533b294cdd5e9e229678be36399b5b28d6541c4bc4e3079fb8a0fbdf6023a65f36c654947ce7c114a243670dad347f03275b5c5bd383e8d53fd0fe8f387ea3d6445fc6510c8a3b9fc5cced503b824504f0471bd3ac19514bdaf7a3c021dc44ca8ff6d656a6007d43b552f07560e8b79217060c1d0c28867433c74f1",
+        ),
+        (
+            b"abc",
+            Some(129),
+            129,
+            "030c9ad6c8f40c7d6c57467b1b050f3d95694dbd57f1be6c04e92007bd503ed9d44851e54711e133b33a04251783b7c9cfa5bcd69f7964657a30a8d369a8b9d9392d9746a1178b49722cc6a4ca456c0267596d4267e3af3744c3e6b91de6b3ec5f28264f8c80820b88323f32401835f7404a2f270c2f0f91d3a503f91c4ec23002",
+        ),
+    ];
+
+    #[test]
+    fn test_blake2xb_known_outputs() {
+        for (data, output_length, read_length, expected) in EXAMPLES {
+            let mut hasher = Blake2xb::new(*output_length).unwrap();
+            hasher.update(data);
+
+            let mut out = vec![0u8; *read_length];
+            hasher.finalize_into(&mut out).unwrap();
+
+            assert_eq!(hex::encode(out), *expected);
+        }
+    }
+
+    #[test]
+    fn test_blake2xb_incremental_reads() {
+        let mut hasher = Blake2xb::new(Some(129)).unwrap();
+        hasher.update(b"abc");
+        let mut expected = vec![0u8; 129];
+        hasher.finalize_into(&mut expected).unwrap();
+
+        for chunk_size in &[1, 7, 63, 64, 65, 200] {
+            let mut reader = hasher.finalize_xof();
+            let mut out = Vec::new();
+            let mut chunk = vec![0u8; *chunk_size];
+            loop {
+                let written = reader.read(&mut chunk);
+                if written == 0 {
+                    break;
+                }
+                out.extend_from_slice(&chunk[..written]);
+            }
+
+            assert_eq!(out, expected);
+            assert_eq!(reader.position(), 129);
+        }
+    }
+
+    #[test]
+    fn test_blake2xb_unknown_length_reader() {
+        let mut hasher = Blake2xb::new(None).unwrap();
+        hasher.update(b"abc");
+
+        // Only the short last block differs from the output of the reference implementation.
+        let mut out = [0u8; 256];
+        assert_eq!(hasher.finalize_xof().read(&mut out), 256);
+        assert_eq!(hex::encode(&out[..192]), EXAMPLES[2].3[..384]);
+        assert_ne!(hex::encode(&out[192..200]), EXAMPLES[2].3[384..]);
+
+        let mut short = [0u8; 0];
+        assert!(hasher.finalize_into(&mut short).is_err());
+    }
+
+    #[test]
+    fn test_blake2xb_options_error() {
+        assert!(Blake2xb::new(Some(0)).is_err());
+        assert!(Blake2xb::new(Some(u32::MAX)).is_err());
+        assert!(Blake2xb::with_options(None, &[0; 65], &[], &[]).is_err());
+        assert!(Blake2xb::with_options(None, &[], &[0; 17], &[]).is_err());
+        assert!(Blake2xb::with_options(None, &[], &[], &[0; 17]).is_err());
+
+        let hasher = Blake2xb::new(Some(100)).unwrap();
+        assert!(hasher.finalize_into(&mut [0u8; 99]).is_err());
+        assert!(hasher.finalize_into(&mut [0u8; 101]).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+use std::convert::TryInto;
+use std::ops::Deref;
+
+use pyo3::buffer::{Element, PyBuffer};
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+/// Read-only elements passed from Python.  Immutable `bytes` objects are read in place.  Anything
+/// else, including other objects supporting the buffer protocol such as `bytearray`, `memoryview`,
+/// `mmap` and `array('Q')`, is copied into an owned vector, since the elements are read with the
+/// GIL released while another thread may write to a mutable buffer.
+pub(crate) enum Elements<T: Element> {
+    Borrowed(PyBuffer<T>),
+    Owned(Vec<T>),
+}
+
+impl<T: Element> Elements<T> {
+    /// Borrow `buffer`, exported by `ob`, in place if its items can never change and can be read
+    /// as a slice, or copy them otherwise.  A read-only buffer is not enough, since a read-only
+    /// view such as `memoryview(bytearray()).toreadonly()` may still be written through the object
+    /// it views.
+    fn from_buffer(ob: &Bound<PyAny>, buffer: PyBuffer<T>) -> PyResult<Self> {
+        let immutable = buffer.readonly() && ob.is_instance_of::<PyBytes>();
+        let aligned = (buffer.buf_ptr() as *const T).is_aligned();
+        if immutable && buffer.is_c_contiguous() && aligned {
+            Ok(Elements::Borrowed(buffer))
+        } else {
+            Ok(Elements::Owned(buffer.to_vec(ob.py())?))
+        }
+    }
+}
+
+impl<T: Element> Default for Elements<T> {
+    fn default() -> Self {
+        Elements::Owned(Vec::new())
+    }
+}
+
+impl<T: Element> Deref for Elements<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match self {
+            Elements::Borrowed(buffer) if buffer.item_count() > 0 => {
+                // The buffer was checked to be immutable, contiguous and aligned when it was
+                // borrowed, and the exporting object keeps its memory alive until the buffer is
+                // released.
+                unsafe {
+                    std::slice::from_raw_parts(buffer.buf_ptr() as *const T, buffer.item_count())
+                }
+            }
+            Elements::Borrowed(_) => &[],
+            Elements::Owned(elements) => elements,
+        }
+    }
+}
+
+impl<T: Element> AsRef<[T]> for Elements<T> {
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+/// Bytes passed from Python as any object supporting the buffer protocol, or as a sequence of
+/// integers.
+pub(crate) type Bytes = Elements<u8>;
+
+impl<'py> FromPyObject<'py> for Bytes {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        match PyBuffer::<u8>::get(ob) {
+            Ok(buffer) => Elements::from_buffer(ob, buffer),
+            Err(_) => Ok(Elements::Owned(ob.extract()?)),
+        }
+    }
+}
+
+/// Words which may be read from the little-endian bytes of a buffer.
+pub(crate) trait Word: Element + for<'py> FromPyObject<'py> {
+    const SIZE: usize;
+
+    fn from_le_slice(bytes: &[u8]) -> Self;
+}
+
+impl Word for u32 {
+    const SIZE: usize = 4;
+
+    fn from_le_slice(bytes: &[u8]) -> Self {
+        u32::from_le_bytes(bytes.try_into().unwrap())
+    }
+}
+
+impl Word for u64 {
+    const SIZE: usize = 8;
+
+    fn from_le_slice(bytes: &[u8]) -> Self {
+        u64::from_le_bytes(bytes.try_into().unwrap())
+    }
+}
+
+/// Words passed from Python as an object supporting the buffer protocol with a matching item
+/// type, as the raw little-endian bytes of the words in any other object supporting the buffer
+/// protocol, or as a sequence of integers.
+pub(crate) type Words<T> = Elements<T>;
+
+impl<'py, T: Word> FromPyObject<'py> for Words<T> {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        if let Ok(buffer) = PyBuffer::<T>::get(ob) {
+            return Elements::from_buffer(ob, buffer);
+        }
+        if let Ok(buffer) = PyBuffer::<u8>::get(ob) {
+            let bytes = Bytes::from_buffer(ob, buffer)?;
+            if bytes.len() % T::SIZE != 0 {
+                return Err(PyValueError::new_err(format!(
+                    "bytes must be a whole number of {}-byte words, got length: {}",
+                    T::SIZE,
+                    bytes.len(),
+                )));
+            }
+
+            return Ok(Elements::Owned(
+                bytes.chunks_exact(T::SIZE).map(T::from_le_slice).collect(),
+            ));
+        }
+
+        Ok(Elements::Owned(ob.extract()?))
+    }
+}
+
+/// A writable buffer passed from Python, holding a blake2b state as 8 64-bit words or as their 64
+/// little-endian bytes.
+pub(crate) enum StateBuffer {
+    Words(PyBuffer<u64>),
+    Bytes(PyBuffer<u8>),
+}
+
+impl<'py> FromPyObject<'py> for StateBuffer {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        let buffer = match PyBuffer::<u64>::get(ob) {
+            Ok(buffer) => StateBuffer::Words(buffer),
+            Err(_) => StateBuffer::Bytes(PyBuffer::<u8>::get(ob)?),
+        };
+
+        let (readonly, item_count, expected) = match &buffer {
+            StateBuffer::Words(buffer) => (buffer.readonly(), buffer.item_count(), 8),
+            StateBuffer::Bytes(buffer) => (buffer.readonly(), buffer.item_count(), 64),
+        };
+        if readonly {
+            return Err(PyTypeError::new_err("state buffer must be writable"));
+        }
+        if item_count != expected {
+            return Err(PyValueError::new_err(format!(
+                "state buffer must hold 8 words or 64 bytes, got: {} {}",
+                item_count,
+                if expected == 8 { "words" } else { "bytes" },
+            )));
+        }
+
+        Ok(buffer)
+    }
+}
+
+impl StateBuffer {
+    /// Read the 8 state words.
+    pub(crate) fn read(&self, py: Python) -> PyResult<[u64; 8]> {
+        let mut state = [0u64; 8];
+        match self {
+            StateBuffer::Words(buffer) => buffer.copy_to_slice(py, &mut state)?,
+            StateBuffer::Bytes(buffer) => {
+                let mut bytes = [0u8; 64];
+                buffer.copy_to_slice(py, &mut bytes)?;
+                for (word, chunk) in state.iter_mut().zip(bytes.chunks_exact(8)) {
+                    *word = u64::from_le_slice(chunk);
+                }
+            }
+        }
+
+        Ok(state)
+    }
+
+    /// Overwrite the buffer with the 8 state words in `state`.
+    pub(crate) fn write(&self, py: Python, state: &[u64; 8]) -> PyResult<()> {
+        match self {
+            StateBuffer::Words(buffer) => buffer.copy_from_slice(py, state),
+            StateBuffer::Bytes(buffer) => {
+                let mut bytes = [0u8; 64];
+                for (chunk, word) in bytes.chunks_exact_mut(8).zip(state.iter()) {
+                    chunk.copy_from_slice(&word.to_le_bytes());
+                }
+                buffer.copy_from_slice(py, &bytes)
+            }
+        }
+    }
+}
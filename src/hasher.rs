@@ -0,0 +1,581 @@
+use std::convert::{TryFrom, TryInto};
+
+use blake2b_core::{compress_with_last_node, Block, Counter, State};
+
+use crate::params::Blake2bParams;
+
+pub use blake2b_core::BLOCKBYTES;
+
+/// The maximum number of bytes in a blake2b digest.
+pub const OUTBYTES: usize = 64;
+
+/// The maximum number of bytes in a blake2b key.
+pub const KEYBYTES: usize = 64;
+
+/// The number of rounds of mixing used by standard blake2b.
+///
+/// See here: https://tools.ietf.org/html/rfc7693#section-3.2
+pub const ROUNDS: usize = 12;
+
+/// The number of bytes in a serialized hasher state.
+pub const STATEBYTES: usize = 220;
+
+/// The version of the serialized hasher state format written by `Blake2b::to_state_bytes`.
+pub const STATE_VERSION: u8 = 1;
+
+/// Define a streaming hasher for a blake2 variant, built on its parameter block builder and on a
+/// `compress` method, implemented separately for each variant, which compresses the buffered block
+/// into the chain value.
+///
+/// Message bytes are buffered until a full block is available.  The last block of a message is
+/// only compressed during finalization since it must be compressed with the final block flag set.
+macro_rules! define_hasher {
+    (
+        $(#[$attr:meta])*
+        pub struct $name:ident {
+            params: $params:ty,
+            word: $word:ty,
+            counter: $counter:ty,
+            blockbytes: $blockbytes:expr,
+            outbytes: $outbytes:expr,
+            keybytes: $keybytes:expr,
+            rounds: $rounds:expr,
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone)]
+        pub struct $name {
+            h: [$word; 8],
+            t: $counter,
+            buf: [u8; $blockbytes],
+            buf_len: usize,
+            digest_size: usize,
+            last_node: bool,
+            rounds: usize,
+        }
+
+        impl $name {
+            /// Create a hasher producing digests of the full `OUTBYTES`.
+            pub fn new() -> Self {
+                Self::with_digest_size($outbytes).unwrap()
+            }
+
+            /// Create a hasher producing digests of `digest_size` bytes, which must be between 1
+            /// and `OUTBYTES`.
+            pub fn with_digest_size(digest_size: usize) -> Result<Self, String> {
+                Self::with_params(&<$params>::new().digest_length(digest_size), &[])
+            }
+
+            /// Create a hasher for keyed hashing with a `key` of between 1 and `KEYBYTES` bytes,
+            /// producing digests of `digest_size` bytes.  This is blake2's native MAC mode, which
+            /// needs no HMAC construction.
+            ///
+            /// See here: https://tools.ietf.org/html/rfc7693#section-3.3
+            pub fn new_keyed(key: &[u8], digest_size: usize) -> Result<Self, String> {
+                if key.is_empty() || key.len() > $keybytes {
+                    return Err(format!(
+                        "key length must be between 1 and {}, got: {}",
+                        $keybytes,
+                        key.len(),
+                    ));
+                }
+
+                let params = <$params>::new()
+                    .digest_length(digest_size)
+                    .key_length(key.len());
+
+                Self::with_params(&params, key)
+            }
+
+            /// Create a hasher using the parameters in `params`.  If `key` is non-empty, it is
+            /// padded to a full block and fed into the hasher ahead of the message.  The key
+            /// length must match the one in `params`.
+            pub fn with_params(params: &$params, key: &[u8]) -> Result<Self, String> {
+                Self::with_params_and_rounds(params, key, $rounds)
+            }
+
+            /// Like `with_params`, except that each block is compressed with `rounds` rounds of
+            /// mixing instead of the standard `ROUNDS`.
+            pub fn with_params_and_rounds(
+                params: &$params,
+                key: &[u8],
+                rounds: usize,
+            ) -> Result<Self, String> {
+                if key.len() != params.get_key_length() {
+                    return Err(format!(
+                        "key length parameter is {}, but key has length: {}",
+                        params.get_key_length(),
+                        key.len(),
+                    ));
+                }
+
+                let mut hasher = Self::with_params_without_key(params, rounds)?;
+                if !key.is_empty() {
+                    hasher.buf[..key.len()].copy_from_slice(key);
+                    hasher.buf_len = $blockbytes;
+                }
+
+                Ok(hasher)
+            }
+
+            /// Create a hasher using the parameters in `params` without feeding in a key block,
+            /// whatever the key length parameter.  The root node of BLAKE2bp is keyed this way.
+            pub(crate) fn with_params_without_key(
+                params: &$params,
+                rounds: usize,
+            ) -> Result<Self, String> {
+                Ok(Self {
+                    h: params.starting_state()?,
+                    t: 0,
+                    buf: [0; $blockbytes],
+                    buf_len: 0,
+                    digest_size: params.get_digest_length(),
+                    last_node: params.is_last_node(),
+                    rounds,
+                })
+            }
+
+            /// The number of bytes in the digest produced by this hasher.
+            pub fn digest_size(&self) -> usize {
+                self.digest_size
+            }
+
+            /// Feed the bytes in `data` into the hasher.
+            pub fn update(&mut self, mut data: &[u8]) {
+                while !data.is_empty() {
+                    // Only compress a full buffer once we know that more data follows it.
+                    // Otherwise, it may be the final block.
+                    if self.buf_len == $blockbytes {
+                        self.compress(false);
+                        self.buf_len = 0;
+                    }
+
+                    let take = ($blockbytes - self.buf_len).min(data.len());
+                    self.buf[self.buf_len..self.buf_len + take].copy_from_slice(&data[..take]);
+                    self.buf_len += take;
+                    data = &data[take..];
+                }
+            }
+
+            /// Return the digest of all bytes fed into the hasher so far.  The hasher itself is
+            /// left unchanged and may continue to be updated.
+            pub fn finalize(&self) -> Vec<u8> {
+                self.finalize_full()[..self.digest_size].to_vec()
+            }
+
+            /// Like `finalize`, except that all `OUTBYTES` of the final state are returned
+            /// whatever the digest size.  Tree hashing modes use this for inner nodes, whose
+            /// output length is the inner hash length rather than the digest length.
+            pub(crate) fn finalize_full(&self) -> [u8; $outbytes] {
+                let mut state = self.clone();
+                for x in state.buf[state.buf_len..].iter_mut() {
+                    *x = 0;
+                }
+                state.compress(true);
+
+                let mut out = [0u8; $outbytes];
+                for (chunk, word) in out
+                    .chunks_exact_mut(std::mem::size_of::<$word>())
+                    .zip(state.h.iter())
+                {
+                    chunk.copy_from_slice(&word.to_le_bytes());
+                }
+
+                out
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+    };
+}
+
+pub(crate) use define_hasher;
+
+define_hasher! {
+    /// A streaming blake2b hasher built on the compression function F.
+    ///
+    /// See here: https://tools.ietf.org/html/rfc7693#section-3.3
+    pub struct Blake2b {
+        params: Blake2bParams,
+        word: u64,
+        counter: u128,
+        blockbytes: BLOCKBYTES,
+        outbytes: OUTBYTES,
+        keybytes: KEYBYTES,
+        rounds: ROUNDS,
+    }
+}
+
+impl Blake2b {
+    /// Check whether `tag` matches the digest of all bytes fed into the hasher so far.  A tag
+    /// shorter than the digest is compared against the leading bytes of the digest, which allows
+    /// truncated MACs to be verified.  An empty tag or one longer than the digest never matches.
+    ///
+    /// The comparison takes the same time wherever the first mismatching byte occurs.
+    pub fn verify(&self, tag: &[u8]) -> bool {
+        let digest = self.finalize();
+        if tag.is_empty() || tag.len() > digest.len() {
+            return false;
+        }
+
+        constant_time_eq(&digest[..tag.len()], tag)
+    }
+
+    /// Serialize the state of the hasher, so that hashing can be resumed later, possibly in
+    /// another process, with `from_state_bytes`.  The format is, with all integers little-endian:
+    ///
+    /// | Offset | Length | Contents                                              |
+    /// |--------|--------|-------------------------------------------------------|
+    /// | 0      | 1      | The format version, currently 1                       |
+    /// | 1      | 1      | The digest size                                       |
+    /// | 2      | 1      | The number of buffered bytes, between 0 and 128       |
+    /// | 3      | 1      | Flags, with bit 0 set for the last node of a tree     |
+    /// | 4      | 8      | The number of rounds of mixing                        |
+    /// | 12     | 64     | The chain value `h`, as 8 64-bit words                |
+    /// | 76     | 16     | The 128-bit counter of bytes compressed so far        |
+    /// | 92     | 128    | The buffered block, padded with zeros                 |
+    ///
+    /// The other parameters are folded into the chain value.  Until more than a block of message
+    /// has been fed into a keyed hasher, the buffered block is the key, so the state must be kept
+    /// as secret as the key.
+    pub fn to_state_bytes(&self) -> [u8; STATEBYTES] {
+        let mut state = [0u8; STATEBYTES];
+        state[0] = STATE_VERSION;
+        state[1] = self.digest_size as u8;
+        state[2] = self.buf_len as u8;
+        state[3] = self.last_node as u8;
+        state[4..12].copy_from_slice(&(self.rounds as u64).to_le_bytes());
+        for (i, word) in self.h.iter().enumerate() {
+            state[12 + i * 8..20 + i * 8].copy_from_slice(&word.to_le_bytes());
+        }
+        state[76..92].copy_from_slice(&self.t.to_le_bytes());
+        state[92..92 + self.buf_len].copy_from_slice(&self.buf[..self.buf_len]);
+
+        state
+    }
+
+    /// Restore a hasher from a state serialized by `to_state_bytes`.
+    pub fn from_state_bytes(state: &[u8]) -> Result<Self, String> {
+        if state.len() != STATEBYTES {
+            return Err(format!(
+                "state must have length {}, got: {}",
+                STATEBYTES,
+                state.len(),
+            ));
+        }
+        if state[0] != STATE_VERSION {
+            return Err(format!(
+                "unsupported state version {}, expected: {}",
+                state[0], STATE_VERSION,
+            ));
+        }
+
+        let digest_size = state[1] as usize;
+        if digest_size == 0 || digest_size > OUTBYTES {
+            return Err(format!(
+                "digest size must be between 1 and {}, got: {}",
+                OUTBYTES, digest_size,
+            ));
+        }
+        let buf_len = state[2] as usize;
+        if buf_len > BLOCKBYTES {
+            return Err(format!(
+                "buffered length must be at most {}, got: {}",
+                BLOCKBYTES, buf_len,
+            ));
+        }
+        if state[3] > 1 {
+            return Err(format!("unknown state flags: {:#04x}", state[3]));
+        }
+        let rounds = u64::from_le_bytes(state[4..12].try_into().unwrap());
+        let rounds = usize::try_from(rounds)
+            .map_err(|_| format!("rounds must be at most {}, got: {}", usize::MAX, rounds))?;
+
+        let mut buf = [0u8; BLOCKBYTES];
+        buf[..buf_len].copy_from_slice(&state[92..92 + buf_len]);
+
+        Ok(Self {
+            h: State::from_bytes(state[12..76].try_into().unwrap()).0,
+            t: u128::from_le_bytes(state[76..92].try_into().unwrap()),
+            buf,
+            buf_len,
+            digest_size,
+            last_node: state[3] == 1,
+            rounds,
+        })
+    }
+
+    fn compress(&mut self, final_block_flag: bool) {
+        self.t = self.t.wrapping_add(self.buf_len as u128);
+
+        self.h = compress_with_last_node(
+            self.rounds,
+            &State(self.h),
+            &Block::from_bytes(&self.buf),
+            Counter(self.t),
+            final_block_flag,
+            final_block_flag && self.last_node,
+        )
+        .0;
+    }
+}
+
+/// Compare the equal length byte slices `a` and `b` without branching on their contents.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let diff = a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y));
+
+    // Keep the compiler from turning the fold into an early exit on the first mismatch.
+    std::hint::black_box(diff) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blake2b(data: &[u8], digest_size: usize) -> Result<Vec<u8>, String> {
+        let mut hasher = Blake2b::with_digest_size(digest_size)?;
+        hasher.update(data);
+        Ok(hasher.finalize())
+    }
+
+    /// Known digests, including that of `b"abc"` given in RFC 7693.
+    ///
+    /// See here: https://tools.ietf.org/html/rfc7693#appendix-A
+    const EXAMPLES: &[(&[u8], usize, &str)] = &[
+        (
+            b"abc",
+            64,
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923",
+        ),
+        (
+            b"",
+            64,
+            "786a02f742015903c6c6fd852552d272912f4740e15847618a86e217f71f5419d25e1031afee585313896444934eb04b903a685b1448b755d56f701afe9be2ce",
+        ),
+        (
+            b"",
+            32,
+            "0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8",
+        ),
+        (
+            b"The quick brown fox jumps over the lazy dog",
+            64,
+            "a8add4bdddfd93e4877d2746e62817b116364a1fa7bc148d95090bc7333b3673f82401cf7aa2e4cb1ecd90296e3f14cb5413f8ed77be73045b13914cdcd6a918",
+        ),
+    ];
+
+    #[test]
+    fn test_hasher_known_digests() {
+        for (data, digest_size, expected) in EXAMPLES {
+            let digest = blake2b(data, *digest_size).unwrap();
+
+            assert_eq!(hex::encode(digest), *expected);
+        }
+    }
+
+    #[test]
+    fn test_hasher_incremental_updates() {
+        let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let expected = blake2b(&data, 64).unwrap();
+
+        for chunk_size in &[1, 7, 127, 128, 129, 256, 999] {
+            let mut hasher = Blake2b::new();
+            for chunk in data.chunks(*chunk_size) {
+                hasher.update(chunk);
+            }
+
+            assert_eq!(hasher.finalize(), expected);
+        }
+    }
+
+    #[test]
+    fn test_hasher_finalize_is_repeatable() {
+        let mut hasher = Blake2b::new();
+        hasher.update(b"a");
+        let first = hasher.finalize();
+
+        assert_eq!(hasher.finalize(), first);
+
+        hasher.update(b"bc");
+
+        assert_eq!(hex::encode(hasher.finalize()), EXAMPLES[0].2);
+    }
+
+    #[test]
+    fn test_hasher_with_params() {
+        let params = Blake2bParams::new()
+            .digest_length(32)
+            .key_length(10)
+            .salt(b"salty")
+            .personal(b"me");
+        let mut hasher = Blake2b::with_params(&params, b"secret key").unwrap();
+        hasher.update(b"abc");
+
+        assert_eq!(
+            hex::encode(hasher.finalize()),
+            "032bbba5959830ae08d50d2c28ef3e6e075a8c6c1cc7af8636eb9ac7bc22778e",
+        );
+
+        let params = Blake2bParams::new()
+            .fanout(2)
+            .depth(3)
+            .leaf_length(4096)
+            .node_offset(5)
+            .node_depth(1)
+            .inner_length(64)
+            .last_node(true);
+        let mut hasher = Blake2b::with_params(&params, &[]).unwrap();
+        hasher.update(b"abc");
+
+        assert_eq!(
+            hex::encode(hasher.finalize()),
+            "6870ee8c393e04f06e3249434d80044a3df0f357421956a1de288d3db8b4d7c6208fc7b8f2e4d8034cba15432417fd9e0d772acc1f9444bba01daf279d9b1f48",
+        );
+    }
+
+    #[test]
+    fn test_hasher_with_params_key_length_mismatch() {
+        let params = Blake2bParams::new().key_length(3);
+
+        assert!(Blake2b::with_params(&params, b"ab").is_err());
+        assert!(Blake2b::with_params(&params, &[]).is_err());
+    }
+
+    #[test]
+    fn test_hasher_digest_size_error() {
+        assert!(Blake2b::with_digest_size(0).is_err());
+        assert!(Blake2b::with_digest_size(65).is_err());
+        assert!(Blake2b::new_keyed(b"key", 0).is_err());
+        assert!(Blake2b::new_keyed(b"key", 65).is_err());
+    }
+
+    /// Keyed digests, including the first keyed known-answer test from the blake2 reference
+    /// implementation.
+    ///
+    /// See here: https://github.com/BLAKE2/BLAKE2/blob/master/testvectors/blake2b-kat.txt
+    const KEYED_EXAMPLES: &[(&[u8], &[u8], usize, &str)] = &[
+        (
+            b"",
+            &[
+                0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22,
+                23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43,
+                44, 45, 46, 47, 48, 49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63,
+            ],
+            64,
+            "10ebb67700b1868efb4417987acf4690ae9d972fb7a590c2f02871799aaa4786b5e996e8f0f4eb981fc214b005f42d2ff4233499391653df7aefcbc13fc51568",
+        ),
+        (
+            b"message",
+            b"k",
+            16,
+            "859bf4acc5ecddf4635a8a5c10e2e0c6",
+        ),
+    ];
+
+    #[test]
+    fn test_keyed_hasher_known_digests() {
+        for (data, key, digest_size, expected) in KEYED_EXAMPLES {
+            let mut hasher = Blake2b::new_keyed(key, *digest_size).unwrap();
+            hasher.update(data);
+
+            assert_eq!(hex::encode(hasher.finalize()), *expected);
+        }
+    }
+
+    #[test]
+    fn test_keyed_hasher_key_length_error() {
+        assert!(Blake2b::new_keyed(&[], 64).is_err());
+        assert!(Blake2b::new_keyed(&[0; 65], 64).is_err());
+    }
+
+    #[test]
+    fn test_keyed_hasher_verify() {
+        let mut hasher = Blake2b::new_keyed(b"k", 16).unwrap();
+        hasher.update(b"message");
+        let tag = hex::decode(KEYED_EXAMPLES[1].3).unwrap();
+
+        assert!(hasher.verify(&tag));
+        assert!(hasher.verify(&tag[..8]));
+        assert!(!hasher.verify(&[]));
+        assert!(!hasher.verify(&[tag.clone(), vec![0]].concat()));
+
+        for i in 0..tag.len() {
+            let mut bad_tag = tag.clone();
+            bad_tag[i] ^= 1;
+
+            assert!(!hasher.verify(&bad_tag));
+        }
+
+        let other = Blake2b::new_keyed(b"other key", 16).unwrap();
+
+        assert!(!other.verify(&tag));
+    }
+
+    #[test]
+    fn test_hasher_state_roundtrip() {
+        let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let params = Blake2bParams::new()
+            .digest_length(32)
+            .key_length(3)
+            .last_node(true);
+
+        for split in &[0, 1, 127, 128, 129, 256, 999, 1000] {
+            let mut expected = Blake2b::with_params_and_rounds(&params, b"key", 20).unwrap();
+            expected.update(&data);
+
+            let mut hasher = Blake2b::with_params_and_rounds(&params, b"key", 20).unwrap();
+            hasher.update(&data[..*split]);
+            let state = hasher.to_state_bytes();
+
+            assert_eq!(state[0], STATE_VERSION);
+            assert_eq!(state[1], 32);
+
+            let mut restored = Blake2b::from_state_bytes(&state).unwrap();
+            restored.update(&data[*split..]);
+
+            assert_eq!(restored.finalize(), expected.finalize());
+            assert_eq!(restored.to_state_bytes()[..], expected.to_state_bytes()[..]);
+        }
+    }
+
+    #[test]
+    fn test_hasher_state_error() {
+        let state = Blake2b::new().to_state_bytes();
+
+        assert!(Blake2b::from_state_bytes(&state[..STATEBYTES - 1]).is_err());
+
+        for (offset, value) in &[(0, 2), (1, 0), (1, 65), (2, 129), (3, 2)] {
+            let mut bad_state = state;
+            bad_state[*offset] = *value;
+
+            assert!(Blake2b::from_state_bytes(&bad_state).is_err());
+        }
+    }
+
+    #[test]
+    fn test_hasher_matches_compress_blocks() {
+        let mut starting_state = blake2b_core::IV;
+        starting_state[0] ^= 0x01010040;
+
+        for length in &[0, 1, 127, 128, 129, 256, 300] {
+            let message: Vec<u8> = (0..*length).map(|i| i as u8).collect();
+            let mut blocks = message.clone();
+            blocks.resize(message.len().div_ceil(128).max(1) * 128, 0);
+            let last_block_length = length - (blocks.len() - 128);
+
+            let mut hasher = Blake2b::new();
+            hasher.update(&message);
+
+            assert_eq!(
+                hasher.finalize(),
+                blake2b_core::compress_blocks(12, &starting_state, &blocks, 0, last_block_length)
+                    .unwrap()
+                    .to_vec(),
+            );
+        }
+    }
+}
@@ -1,165 +1,29 @@
+//! Blake2b hashing built on the compression function F from the `blake2b-core` crate, which is
+//! re-exported here along with its `State`, `Block` and `Counter` types.  `compress` runs F on
+//! these fixed-size types so that no call can fail.  Arguments from elsewhere, such as the
+//! tightly packed input of the blake2 F precompile from EIP-152, are checked as they are converted
+//! and any problem is returned as a `CompressError`.
+//!
+//! This crate adds the hashers, tree hashing and batch compression, which need `std`.  Python
+//! bindings are built on top of it when the `python` feature is enabled.
 #![cfg_attr(test, feature(test))]
 
-mod blake2b;
-
-use pyo3::exceptions::PyValueError;
-use pyo3::prelude::*;
-use pyo3::types::PyBytes;
-use pyo3::wrap_pyfunction;
-
-type CompressArgs = (usize, Vec<u64>, Vec<u64>, Vec<u64>, bool);
-
-/// decode_parameters(input)
-/// --
-///
-/// Decode parameters for the ``compress`` function from the tightly packed
-/// encoding in the byte sequence `input`.
-///
-/// Parameters
-/// ----------
-/// input : bytes, List[int]
-///     A vector of 213 bytes representing the tightly encoded input.
-///
-/// Returns
-/// ----------
-/// out : (int, List[int], List[int], List[int], bool)
-///     A tuple of parameters to pass to the ``compress`` function.
-#[pyfunction]
-fn decode_parameters(input: Vec<u8>) -> PyResult<CompressArgs> {
-    let result = blake2b::decode_parameters(&input);
-
-    match result {
-        Err(msg) => Err(PyValueError::new_err(msg)),
-        Ok(args) => {
-            let (rounds, state, block, offsets, flag) = args;
-            Ok((
-                rounds,
-                state.to_vec(),
-                block.to_vec(),
-                offsets.to_vec(),
-                flag,
-            ))
-        }
-    }
-}
-
-fn checked_compress(
-    rounds: usize,
-    starting_state: &[u64],
-    block: &[u64],
-    offset_counters: &[u64],
-    final_block_flag: bool,
-) -> Result<[u8; 64], String> {
-    if starting_state.len() != 8 {
-        return Err(format!(
-            "starting state vector must have length 8, got: {}",
-            starting_state.len(),
-        ));
-    }
-    if block.len() != 16 {
-        return Err(format!(
-            "block vector must have length 16, got: {}",
-            block.len(),
-        ));
-    }
-    if offset_counters.len() != 2 {
-        return Err(format!(
-            "offset counters vector must have length 2, got: {}",
-            offset_counters.len(),
-        ));
-    }
-
-    Ok(blake2b::F(
-        rounds,
-        starting_state,
-        block,
-        offset_counters,
-        final_block_flag,
-    ))
-}
-
-/// compress(rounds, starting_state, block, offset_counters, final_block_flag)
-/// --
-///
-/// Calculates a blake2b hash for the given message block.
-///
-/// Parameters
-/// ----------
-/// rounds : int
-///     The number of rounds of mixing to occur during hashing.
-/// starting_state : List[int]
-///     A vector of 8 64-bit integers representing the starting state of the
-///     hash function.
-/// block : List[int]
-///     A vector of 16 64-bit integers representing the message block to be hashed.
-/// offset_counters : List[int]
-///     A vector of 2 64-bit integers representing the message byte offset at
-///     the end of the current block.
-/// final_block_flag : bool
-///     A flag indicating the final block of the message.
-///
-/// Returns
-/// -------
-/// out : bytes
-///     A vector of 64 bytes representing the blake2b hash of the input data.
-#[pyfunction]
-fn compress(
-    py: Python,
-    rounds: usize,
-    starting_state: Vec<u64>,
-    block: Vec<u64>,
-    offset_counters: Vec<u64>,
-    final_block_flag: bool,
-) -> PyResult<PyObject> {
-    let result = checked_compress(
-        rounds,
-        &starting_state,
-        &block,
-        &offset_counters,
-        final_block_flag,
-    );
-
-    match result {
-        Err(msg) => Err(PyValueError::new_err(msg)),
-        Ok(ok) => Ok(PyBytes::new(py, &ok).into()),
-    }
-}
-
-fn _decode_and_compress(input: Vec<u8>) -> Result<[u8; 64], String> {
-    let (r, h, m, t, f) = blake2b::decode_parameters(&input)?;
-    checked_compress(r, &h, &m, &t, f)
-}
-
-/// decode_and_compress(input)
-/// --
-///
-/// Calculates a blake2b hash for the tightly encoded input given in the byte
-/// sequence `input`.
-///
-/// Parameters
-/// ----------
-/// input : bytes, List[int]
-///     A vector of 213 bytes representing the tightly encoded input.
-///
-/// Returns
-/// -------
-/// out : bytes
-///     A vector of 64 bytes representing the blake2b hash of the input data.
-#[pyfunction]
-fn decode_and_compress(py: Python, input: Vec<u8>) -> PyResult<PyObject> {
-    let result = _decode_and_compress(input);
-
-    match result {
-        Err(msg) => Err(PyValueError::new_err(msg)),
-        Ok(ok) => Ok(PyBytes::new(py, &ok).into()),
-    }
-}
-
-/// Functions for calculating blake2b hashes.
-#[pymodule(name = "blake2b")]
-fn blake2b_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
-    m.add_function(wrap_pyfunction!(decode_parameters, m)?)?;
-    m.add_function(wrap_pyfunction!(compress, m)?)?;
-    m.add_function(wrap_pyfunction!(decode_and_compress, m)?)?;
-    Ok(())
-}
+pub mod batch;
+pub mod blake2bp;
+pub mod blake2s;
+pub mod blake2x;
+#[cfg(feature = "python")]
+mod buffer;
+pub mod hasher;
+pub mod params;
+#[cfg(feature = "python")]
+mod python;
+pub mod tree;
+
+pub use blake2b_core::{backend, error, precompile};
+pub use blake2b_core::{
+    compress, compress_blocks, compress_lanes, compress_with_checks, compress_with_last_node,
+    decode_parameters, encode_parameters, Blake2FParams, Block, CompressError, Counter, State, IV,
+};
+
+pub use crate::batch::compress_many;
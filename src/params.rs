@@ -0,0 +1,387 @@
+use blake2b_core::{State, IV};
+
+use crate::hasher::{KEYBYTES, OUTBYTES};
+
+/// The number of bytes in a blake2b parameter block.
+pub const PARAMBYTES: usize = 64;
+
+/// The number of bytes in a blake2b salt.
+pub const SALTBYTES: usize = 16;
+
+/// The number of bytes in a blake2b personalization string.
+pub const PERSONALBYTES: usize = 16;
+
+/// Define a builder for the parameter block of a blake2 variant, which is XORed into the
+/// initialization vector to give the starting state passed to the compression function F.  Blake2b
+/// and blake2s share the same fields and encoding, except for the word size and the number of bytes
+/// given to the node offset, salt and personalization string.
+///
+/// Setters accept any value and all fields are validated together when the parameter block is
+/// encoded.  Salts and personalization strings shorter than the maximum are padded with zeros.
+macro_rules! define_params {
+    (
+        $(#[$attr:meta])*
+        pub struct $name:ident {
+            word: $word:ty,
+            iv: $iv:expr,
+            parameter_words: $parameter_words:path,
+            outbytes: $outbytes:expr,
+            keybytes: $keybytes:expr,
+            parambytes: $parambytes:expr,
+            saltbytes: $saltbytes:expr,
+            personalbytes: $personalbytes:expr,
+            node_offset_bytes: $node_offset_bytes:expr,
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub struct $name {
+            digest_length: usize,
+            key_length: usize,
+            fanout: usize,
+            depth: usize,
+            leaf_length: u64,
+            node_offset: u64,
+            node_depth: usize,
+            inner_length: usize,
+            salt: [u8; $saltbytes],
+            salt_length: usize,
+            personal: [u8; $personalbytes],
+            personal_length: usize,
+            last_node: bool,
+        }
+
+        impl $name {
+            /// The largest node offset that fits in the parameter block.
+            const MAX_NODE_OFFSET: u64 = u64::MAX >> (64 - 8 * $node_offset_bytes);
+
+            /// Create parameters for a sequential, unkeyed hash with a digest of `OUTBYTES`.
+            pub fn new() -> Self {
+                Self {
+                    digest_length: $outbytes,
+                    key_length: 0,
+                    fanout: 1,
+                    depth: 1,
+                    leaf_length: 0,
+                    node_offset: 0,
+                    node_depth: 0,
+                    inner_length: 0,
+                    salt: [0; $saltbytes],
+                    salt_length: 0,
+                    personal: [0; $personalbytes],
+                    personal_length: 0,
+                    last_node: false,
+                }
+            }
+
+            /// Set the digest length in bytes, between 1 and `OUTBYTES`.
+            pub fn digest_length(mut self, digest_length: usize) -> Self {
+                self.digest_length = digest_length;
+                self
+            }
+
+            /// Set the key length in bytes, between 0 (unkeyed) and `KEYBYTES`.
+            pub fn key_length(mut self, key_length: usize) -> Self {
+                self.key_length = key_length;
+                self
+            }
+
+            /// Set the tree hashing fanout, between 0 (unlimited) and 255.
+            pub fn fanout(mut self, fanout: usize) -> Self {
+                self.fanout = fanout;
+                self
+            }
+
+            /// Set the maximal tree hashing depth, between 1 and 255.
+            pub fn depth(mut self, depth: usize) -> Self {
+                self.depth = depth;
+                self
+            }
+
+            /// Set the tree hashing leaf length in bytes, between 0 (unlimited) and 2 ** 32 - 1.
+            pub fn leaf_length(mut self, leaf_length: u64) -> Self {
+                self.leaf_length = leaf_length;
+                self
+            }
+
+            /// Set the tree hashing node offset.
+            pub fn node_offset(mut self, node_offset: u64) -> Self {
+                self.node_offset = node_offset;
+                self
+            }
+
+            /// Set the tree hashing node depth, between 0 (leaves) and 255.
+            pub fn node_depth(mut self, node_depth: usize) -> Self {
+                self.node_depth = node_depth;
+                self
+            }
+
+            /// Set the tree hashing inner hash length in bytes, between 0 and `OUTBYTES`.
+            pub fn inner_length(mut self, inner_length: usize) -> Self {
+                self.inner_length = inner_length;
+                self
+            }
+
+            /// Set the salt, of at most `SALTBYTES` bytes.
+            pub fn salt(mut self, salt: &[u8]) -> Self {
+                let n = salt.len().min($saltbytes);
+                self.salt = [0; $saltbytes];
+                self.salt[..n].copy_from_slice(&salt[..n]);
+                self.salt_length = salt.len();
+                self
+            }
+
+            /// Set the personalization string, of at most `PERSONALBYTES` bytes.
+            pub fn personal(mut self, personal: &[u8]) -> Self {
+                let n = personal.len().min($personalbytes);
+                self.personal = [0; $personalbytes];
+                self.personal[..n].copy_from_slice(&personal[..n]);
+                self.personal_length = personal.len();
+                self
+            }
+
+            /// Set whether a hash using these parameters is the last node at its level of a hash
+            /// tree.  This is not part of the parameter block.  Instead, it sets the last node flag
+            /// f1 when the final block is compressed.
+            pub fn last_node(mut self, last_node: bool) -> Self {
+                self.last_node = last_node;
+                self
+            }
+
+            /// The digest length in bytes.
+            pub fn get_digest_length(&self) -> usize {
+                self.digest_length
+            }
+
+            /// The key length in bytes.
+            pub fn get_key_length(&self) -> usize {
+                self.key_length
+            }
+
+            /// The tree hashing fanout.
+            pub fn get_fanout(&self) -> usize {
+                self.fanout
+            }
+
+            /// The maximal tree hashing depth.
+            pub fn get_depth(&self) -> usize {
+                self.depth
+            }
+
+            /// The tree hashing leaf length in bytes.
+            pub fn get_leaf_length(&self) -> u64 {
+                self.leaf_length
+            }
+
+            /// The tree hashing inner hash length in bytes.
+            pub fn get_inner_length(&self) -> usize {
+                self.inner_length
+            }
+
+            /// Whether the last node flag is set.
+            pub fn is_last_node(&self) -> bool {
+                self.last_node
+            }
+
+            /// Check that all parameters are within the ranges allowed by the parameter block
+            /// encoding.
+            pub fn validate(&self) -> Result<(), String> {
+                if self.digest_length == 0 || self.digest_length > $outbytes {
+                    return Err(format!(
+                        "digest length must be between 1 and {}, got: {}",
+                        $outbytes, self.digest_length,
+                    ));
+                }
+                if self.key_length > $keybytes {
+                    return Err(format!(
+                        "key length must be at most {}, got: {}",
+                        $keybytes, self.key_length,
+                    ));
+                }
+                if self.fanout > 255 {
+                    return Err(format!(
+                        "fanout must be between 0 and 255, got: {}",
+                        self.fanout,
+                    ));
+                }
+                if self.depth == 0 || self.depth > 255 {
+                    return Err(format!(
+                        "depth must be between 1 and 255, got: {}",
+                        self.depth,
+                    ));
+                }
+                if self.leaf_length > u64::from(u32::MAX) {
+                    return Err(format!(
+                        "leaf length must be between 0 and {}, got: {}",
+                        u32::MAX,
+                        self.leaf_length,
+                    ));
+                }
+                if self.node_offset > Self::MAX_NODE_OFFSET {
+                    return Err(format!(
+                        "node offset must be between 0 and {}, got: {}",
+                        Self::MAX_NODE_OFFSET,
+                        self.node_offset,
+                    ));
+                }
+                if self.node_depth > 255 {
+                    return Err(format!(
+                        "node depth must be between 0 and 255, got: {}",
+                        self.node_depth,
+                    ));
+                }
+                if self.inner_length > $outbytes {
+                    return Err(format!(
+                        "inner length must be between 0 and {}, got: {}",
+                        $outbytes, self.inner_length,
+                    ));
+                }
+                if self.salt_length > $saltbytes {
+                    return Err(format!(
+                        "salt length must be at most {}, got: {}",
+                        $saltbytes, self.salt_length,
+                    ));
+                }
+                if self.personal_length > $personalbytes {
+                    return Err(format!(
+                        "personalization length must be at most {}, got: {}",
+                        $personalbytes, self.personal_length,
+                    ));
+                }
+
+                Ok(())
+            }
+
+            /// Encode the parameter block of `PARAMBYTES` bytes.
+            ///
+            /// See here: https://tools.ietf.org/html/rfc7693#section-2.5
+            pub fn to_bytes(&self) -> Result<[u8; $parambytes], String> {
+                self.validate()?;
+
+                let offset_end = 8 + $node_offset_bytes;
+                let salt_start = $parambytes - $saltbytes - $personalbytes;
+
+                let mut parameter_block = [0u8; $parambytes];
+                parameter_block[0] = self.digest_length as u8;
+                parameter_block[1] = self.key_length as u8;
+                parameter_block[2] = self.fanout as u8;
+                parameter_block[3] = self.depth as u8;
+                parameter_block[4..8].copy_from_slice(&(self.leaf_length as u32).to_le_bytes());
+                parameter_block[8..offset_end]
+                    .copy_from_slice(&self.node_offset.to_le_bytes()[..$node_offset_bytes]);
+                parameter_block[offset_end] = self.node_depth as u8;
+                parameter_block[offset_end + 1] = self.inner_length as u8;
+                // Any bytes between the inner length and the salt are reserved and left as zeros.
+                parameter_block[salt_start..salt_start + $saltbytes].copy_from_slice(&self.salt);
+                parameter_block[$parambytes - $personalbytes..].copy_from_slice(&self.personal);
+
+                Ok(parameter_block)
+            }
+
+            /// Calculate the starting state for the compression function F by XORing the
+            /// parameter block into the initialization vector.
+            pub fn starting_state(&self) -> Result<[$word; 8], String> {
+                let parameter_words = $parameter_words(&self.to_bytes()?);
+
+                let mut h = $iv;
+                for (word, parameter_word) in h.iter_mut().zip(parameter_words.iter()) {
+                    *word ^= parameter_word;
+                }
+
+                Ok(h)
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+    };
+}
+
+pub(crate) use define_params;
+
+define_params! {
+    /// A builder for the blake2b parameter block.
+    ///
+    /// See here: https://blake2.net/blake2.pdf (section 2.8)
+    pub struct Blake2bParams {
+        word: u64,
+        iv: IV,
+        parameter_words: parameter_words,
+        outbytes: OUTBYTES,
+        keybytes: KEYBYTES,
+        parambytes: PARAMBYTES,
+        saltbytes: SALTBYTES,
+        personalbytes: PERSONALBYTES,
+        node_offset_bytes: 8,
+    }
+}
+
+fn parameter_words(parameter_block: &[u8; PARAMBYTES]) -> [u64; 8] {
+    State::from_bytes(parameter_block).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_params_default_starting_state() {
+        let h = Blake2bParams::new().starting_state().unwrap();
+
+        assert_eq!(h[0], IV[0] ^ 0x0101_0040);
+        assert_eq!(h[1..], IV[1..]);
+    }
+
+    #[test]
+    fn test_params_to_bytes() {
+        let parameter_block = Blake2bParams::new()
+            .digest_length(32)
+            .key_length(10)
+            .fanout(2)
+            .depth(3)
+            .leaf_length(4096)
+            .node_offset(5)
+            .node_depth(1)
+            .inner_length(64)
+            .salt(b"salty")
+            .personal(b"me")
+            .to_bytes()
+            .unwrap();
+
+        let mut expected = [0u8; PARAMBYTES];
+        expected[..4].copy_from_slice(&[32, 10, 2, 3]);
+        expected[4..8].copy_from_slice(&4096u32.to_le_bytes());
+        expected[8..16].copy_from_slice(&5u64.to_le_bytes());
+        expected[16] = 1;
+        expected[17] = 64;
+        expected[32..37].copy_from_slice(b"salty");
+        expected[48..50].copy_from_slice(b"me");
+
+        assert_eq!(parameter_block[..], expected[..]);
+    }
+
+    #[test]
+    fn test_params_validation_error() {
+        let invalid = [
+            Blake2bParams::new().digest_length(0),
+            Blake2bParams::new().digest_length(65),
+            Blake2bParams::new().key_length(65),
+            Blake2bParams::new().fanout(256),
+            Blake2bParams::new().depth(0),
+            Blake2bParams::new().depth(256),
+            Blake2bParams::new().leaf_length(1 << 32),
+            Blake2bParams::new().node_depth(256),
+            Blake2bParams::new().inner_length(65),
+            Blake2bParams::new().salt(&[0; 17]),
+            Blake2bParams::new().personal(&[0; 17]),
+        ];
+
+        for params in invalid.iter() {
+            assert!(params.to_bytes().is_err());
+            assert!(params.starting_state().is_err());
+        }
+    }
+}
@@ -0,0 +1,2148 @@
+use std::collections::hash_map::DefaultHasher;
+use std::convert::{TryFrom, TryInto};
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyMemoryError, PyTimeoutError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::pyclass::CompareOp;
+use pyo3::types::{PyBool, PyBytes, PyIterator};
+use pyo3::wrap_pyfunction;
+
+use blake2b_core::{self as blake2b, backend, error, precompile};
+
+use crate::{batch, blake2bp, blake2s, blake2x, buffer, hasher, params, tree};
+
+/// The tuple form of ``Blake2FParams``, which ``decode_parameters`` used to return.
+type CompressArgs = (usize, Vec<u64>, Vec<u64>, Vec<u64>, bool);
+
+create_exception!(
+    blake2b,
+    CompressError,
+    PyValueError,
+    "Raised for invalid arguments to a compression function, either decoded from the tightly encoded input or given directly."
+);
+create_exception!(
+    blake2b,
+    InvalidInputLengthError,
+    CompressError,
+    "Raised when the tightly encoded input does not have ``expected`` bytes.  The actual number of bytes is given by ``length``."
+);
+create_exception!(
+    blake2b,
+    InvalidFinalBlockFlagError,
+    CompressError,
+    "Raised when the final block indicator flag at ``offset`` in the tightly encoded input is neither 0 nor 1.  The flag byte is given by ``value``."
+);
+create_exception!(
+    blake2b,
+    InvalidStartingStateLengthError,
+    CompressError,
+    "Raised when the starting state vector does not have ``expected`` words.  The actual number of words is given by ``length``."
+);
+create_exception!(
+    blake2b,
+    InvalidBlockLengthError,
+    CompressError,
+    "Raised when the block vector does not have ``expected`` words.  The actual number of words is given by ``length``."
+);
+create_exception!(
+    blake2b,
+    InvalidOffsetCountersLengthError,
+    CompressError,
+    "Raised when the offset counters vector does not have ``expected`` words.  The actual number of words is given by ``length``."
+);
+create_exception!(
+    blake2b,
+    InvalidRoundsError,
+    CompressError,
+    "Raised when the number of ``rounds`` is more than the ``maximum`` which fits in the tightly encoded input."
+);
+create_exception!(
+    blake2b,
+    InvalidBlocksLengthError,
+    CompressError,
+    "Raised when the blocks given to ``compress_blocks`` are not a non-zero whole number of ``block_size`` bytes.  The actual number of bytes is given by ``length``."
+);
+create_exception!(
+    blake2b,
+    InvalidLastBlockLengthError,
+    CompressError,
+    "Raised when the ``length`` of the last block given to ``compress_blocks`` is more than the ``maximum`` of 128 bytes."
+);
+create_exception!(
+    blake2b,
+    RoundBudgetExceededError,
+    PyValueError,
+    "Raised when the number of ``rounds`` is more than the budget of ``max_rounds`` given to ``compress`` or ``decode_and_compress``."
+);
+create_exception!(
+    blake2b,
+    OutOfGasError,
+    PyValueError,
+    "Raised by ``blake2f_precompile`` when ``gas_limit`` does not cover the ``gas_required`` by the rounds in the input."
+);
+
+/// Set the attribute values in `fields` on the Python exception `err`.
+fn exception_with_fields(err: PyErr, fields: &[(&str, u64)]) -> PyErr {
+    Python::with_gil(|py| {
+        let value = err.value(py);
+        for (name, field) in fields.iter() {
+            // Setting an attribute on a fresh exception instance cannot fail.
+            value.setattr(*name, *field).unwrap();
+        }
+    });
+
+    err
+}
+
+/// Convert a compression function argument error into the matching Python exception, with the
+/// fields of the error as attributes.
+fn compress_error(err: error::CompressError) -> PyErr {
+    let message = err.to_string();
+
+    match err {
+        error::CompressError::InvalidInputLength { length } => exception_with_fields(
+            InvalidInputLengthError::new_err(message),
+            &[
+                ("length", length as u64),
+                ("expected", error::INPUT_LENGTH as u64),
+            ],
+        ),
+        error::CompressError::InvalidFinalBlockFlag { value, offset } => exception_with_fields(
+            InvalidFinalBlockFlagError::new_err(message),
+            &[("value", value.into()), ("offset", offset as u64)],
+        ),
+        error::CompressError::InvalidStartingStateLength { length } => exception_with_fields(
+            InvalidStartingStateLengthError::new_err(message),
+            &[("length", length as u64), ("expected", 8)],
+        ),
+        error::CompressError::InvalidBlockLength { length } => exception_with_fields(
+            InvalidBlockLengthError::new_err(message),
+            &[("length", length as u64), ("expected", 16)],
+        ),
+        error::CompressError::InvalidOffsetCountersLength { length } => exception_with_fields(
+            InvalidOffsetCountersLengthError::new_err(message),
+            &[("length", length as u64), ("expected", 2)],
+        ),
+        error::CompressError::InvalidRounds { rounds } => exception_with_fields(
+            InvalidRoundsError::new_err(message),
+            &[("rounds", rounds as u64), ("maximum", u32::MAX.into())],
+        ),
+        error::CompressError::InvalidBlocksLength { length } => exception_with_fields(
+            InvalidBlocksLengthError::new_err(message),
+            &[("length", length as u64), ("block_size", 128)],
+        ),
+        error::CompressError::InvalidLastBlockLength { length } => exception_with_fields(
+            InvalidLastBlockLengthError::new_err(message),
+            &[("length", length as u64), ("maximum", 128)],
+        ),
+    }
+}
+
+/// decode_parameters(input)
+/// --
+///
+/// Decode parameters for the ``compress`` function from the tightly packed
+/// encoding in the byte sequence `input`.
+///
+/// Parameters
+/// ----------
+/// input : bytes, List[int]
+///     A vector of 213 bytes representing the tightly encoded input.
+///
+/// Returns
+/// ----------
+/// out : Blake2FParams
+///     The parameters to pass to the ``compress`` function, which may be
+///     unpacked as a tuple.
+#[pyfunction]
+fn decode_parameters(input: buffer::Bytes) -> PyResult<PyBlake2FParams> {
+    let result = blake2b::decode_parameters(&input);
+
+    match result {
+        Err(err) => Err(compress_error(err)),
+        Ok(params) => Ok(PyBlake2FParams { params }),
+    }
+}
+
+/// encode_parameters(rounds, starting_state, block, offset_counters, final_block_flag)
+/// --
+///
+/// Encode parameters for the ``compress`` function into the tightly packed
+/// encoding accepted by ``decode_parameters``, with big-endian rounds and
+/// little-endian words.
+///
+/// Parameters
+/// ----------
+/// rounds : int
+///     The number of rounds of mixing, between 0 and 2 ** 32 - 1.
+/// starting_state : List[int], bytes
+///     A vector of 8 64-bit integers representing the starting state of the
+///     hash function.
+/// block : List[int], bytes
+///     A vector of 16 64-bit integers representing the message block to be hashed.
+/// offset_counters : List[int], bytes
+///     A vector of 2 64-bit integers representing the message byte offset at
+///     the end of the current block.
+/// final_block_flag : bool
+///     A flag indicating the final block of the message.
+///
+/// Returns
+/// -------
+/// out : bytes
+///     A vector of 213 bytes representing the tightly encoded input.
+#[pyfunction]
+fn encode_parameters(
+    py: Python,
+    rounds: usize,
+    starting_state: buffer::Words<u64>,
+    block: buffer::Words<u64>,
+    offset_counters: buffer::Words<u64>,
+    final_block_flag: bool,
+) -> PyResult<PyObject> {
+    let result = blake2b::encode_parameters(
+        rounds,
+        &starting_state,
+        &block,
+        &offset_counters,
+        final_block_flag,
+    );
+
+    match result {
+        Err(err) => Err(compress_error(err)),
+        Ok(ok) => Ok(PyBytes::new(py, &ok).into()),
+    }
+}
+
+/// Convert the vector arguments to a compression function into the types taken by the library,
+/// checking their lengths.  The arguments are copied so that they can't change while the GIL is
+/// released.
+fn compress_args(
+    starting_state: &[u64],
+    block: &[u64],
+    offset_counters: &[u64],
+) -> Result<(blake2b::State, blake2b::Block, blake2b::Counter), error::CompressError> {
+    Ok((
+        starting_state.try_into()?,
+        block.try_into()?,
+        offset_counters.try_into()?,
+    ))
+}
+
+/// The default number of rounds between checks for interruption of long running compression.
+const DEFAULT_CHECK_INTERVAL: usize = 1 << 20;
+
+/// Options for long running compression, which allow it to be abandoned before all rounds of
+/// mixing are done.
+struct CompressOptions {
+    check_interval: Option<usize>,
+    timeout: Option<f64>,
+    max_rounds: Option<usize>,
+    progress: Option<PyObject>,
+}
+
+impl CompressOptions {
+    /// Run the compression function F with the GIL released, checking for interruption if any of
+    /// the check interval, timeout or progress callback is set.
+    fn compress(
+        self,
+        py: Python,
+        rounds: usize,
+        starting_state: &[u64],
+        block: &[u64],
+        offset_counters: &[u64],
+        final_block_flag: bool,
+    ) -> PyResult<[u8; 64]> {
+        if let Some(max_rounds) = self.max_rounds {
+            if rounds > max_rounds {
+                return Err(exception_with_fields(
+                    RoundBudgetExceededError::new_err(format!(
+                        "rounds must be at most the budget of {}, got: {}",
+                        max_rounds, rounds,
+                    )),
+                    &[("rounds", rounds as u64), ("max_rounds", max_rounds as u64)],
+                ));
+            }
+        }
+
+        let (state, block, counter) =
+            compress_args(starting_state, block, offset_counters).map_err(compress_error)?;
+
+        if self.check_interval.is_none() && self.timeout.is_none() && self.progress.is_none() {
+            return Ok(py.allow_threads(|| {
+                blake2b::compress(rounds, &state, &block, counter, final_block_flag).to_bytes()
+            }));
+        }
+
+        let check_interval = self.check_interval.unwrap_or(DEFAULT_CHECK_INTERVAL);
+        if check_interval == 0 {
+            return Err(PyValueError::new_err(
+                "check interval must be at least 1 round",
+            ));
+        }
+        let deadline = match self.timeout {
+            None => None,
+            Some(timeout) => {
+                let timeout = Duration::try_from_secs_f64(timeout).map_err(|_| {
+                    PyValueError::new_err(format!(
+                        "timeout must be a non-negative number of seconds, got: {}",
+                        timeout,
+                    ))
+                })?;
+                // A deadline too far in the future to represent is never reached.
+                Instant::now().checked_add(timeout)
+            }
+        };
+        let progress = self.progress;
+
+        py.allow_threads(|| {
+            blake2b::compress_with_checks(
+                rounds,
+                &state,
+                &block,
+                counter,
+                final_block_flag,
+                check_interval,
+                |rounds_done| {
+                    Python::with_gil(|py| {
+                        py.check_signals()?;
+                        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                            return Err(PyTimeoutError::new_err(format!(
+                                "compression timed out after {} of {} rounds",
+                                rounds_done, rounds,
+                            )));
+                        }
+                        if let Some(progress) = &progress {
+                            progress.call1(py, (rounds_done, rounds))?;
+                        }
+
+                        Ok(())
+                    })
+                },
+            )
+            .map(|state| state.to_bytes())
+        })
+    }
+}
+
+/// compress(rounds, starting_state, block, offset_counters, final_block_flag, *, check_interval=None, timeout=None, max_rounds=None, progress=None)
+/// --
+///
+/// Calculates a blake2b hash for the given message block.  The GIL is
+/// released while the rounds of mixing are done, so that other Python threads
+/// keep running during long computations.
+///
+/// Vectors may be given as sequences of integers, as buffers of 64-bit words
+/// such as ``array('Q')``, or as the little-endian bytes of the words in any
+/// other object supporting the buffer protocol.
+///
+/// If any of `check_interval`, `timeout` or `progress` is given, the
+/// computation may be abandoned before all rounds are done.  Every
+/// `check_interval` rounds, Python signal handlers are run, so that Ctrl-C
+/// raises ``KeyboardInterrupt``, the deadline is checked and `progress` is
+/// called.
+///
+/// Parameters
+/// ----------
+/// rounds : int
+///     The number of rounds of mixing to occur during hashing.
+/// starting_state : List[int], bytes
+///     A vector of 8 64-bit integers representing the starting state of the
+///     hash function.
+/// block : List[int], bytes
+///     A vector of 16 64-bit integers representing the message block to be hashed.
+/// offset_counters : List[int], bytes
+///     A vector of 2 64-bit integers representing the message byte offset at
+///     the end of the current block.
+/// final_block_flag : bool
+///     A flag indicating the final block of the message.
+/// check_interval : int, optional
+///     The number of rounds between checks, which defaults to 2 ** 20.
+/// timeout : float, optional
+///     The number of seconds after which ``TimeoutError`` is raised.
+/// max_rounds : int, optional
+///     The budget of rounds, above which ``RoundBudgetExceededError`` is
+///     raised before any rounds are done.
+/// progress : Callable[[int, int], None], optional
+///     Called with the number of rounds done and the total number of rounds.
+///     Any exception it raises abandons the computation.
+///
+/// Returns
+/// -------
+/// out : bytes
+///     A vector of 64 bytes representing the blake2b hash of the input data.
+#[pyfunction]
+#[pyo3(signature = (
+    rounds,
+    starting_state,
+    block,
+    offset_counters,
+    final_block_flag,
+    *,
+    check_interval=None,
+    timeout=None,
+    max_rounds=None,
+    progress=None,
+))]
+#[allow(clippy::too_many_arguments)]
+fn compress(
+    py: Python,
+    rounds: usize,
+    starting_state: buffer::Words<u64>,
+    block: buffer::Words<u64>,
+    offset_counters: buffer::Words<u64>,
+    final_block_flag: bool,
+    check_interval: Option<usize>,
+    timeout: Option<f64>,
+    max_rounds: Option<usize>,
+    progress: Option<PyObject>,
+) -> PyResult<PyObject> {
+    let options = CompressOptions {
+        check_interval,
+        timeout,
+        max_rounds,
+        progress,
+    };
+    let result = options.compress(
+        py,
+        rounds,
+        &starting_state,
+        &block,
+        &offset_counters,
+        final_block_flag,
+    )?;
+
+    Ok(PyBytes::new(py, &result).into())
+}
+
+/// compress_with_last_node(rounds, starting_state, block, offset_counters, final_block_flag, last_node_flag)
+/// --
+///
+/// Like ``compress`` except the last node flag f1 may also be set, as needed
+/// for the final block of the last node at each level of a hash tree, such as
+/// the root of a BLAKE2bp or tree mode hash.
+///
+/// Parameters
+/// ----------
+/// rounds : int
+///     The number of rounds of mixing to occur during hashing.
+/// starting_state : List[int], bytes
+///     A vector of 8 64-bit integers representing the starting state of the
+///     hash function.
+/// block : List[int], bytes
+///     A vector of 16 64-bit integers representing the message block to be hashed.
+/// offset_counters : List[int], bytes
+///     A vector of 2 64-bit integers representing the message byte offset at
+///     the end of the current block.
+/// final_block_flag : bool
+///     A flag indicating the final block of the message.
+/// last_node_flag : bool
+///     A flag indicating the last node at a level of a hash tree.
+///
+/// Returns
+/// -------
+/// out : bytes
+///     A vector of 64 bytes representing the blake2b hash of the input data.
+#[pyfunction]
+fn compress_with_last_node(
+    py: Python,
+    rounds: usize,
+    starting_state: buffer::Words<u64>,
+    block: buffer::Words<u64>,
+    offset_counters: buffer::Words<u64>,
+    final_block_flag: bool,
+    last_node_flag: bool,
+) -> PyResult<PyObject> {
+    let (state, block, counter) =
+        compress_args(&starting_state, &block, &offset_counters).map_err(compress_error)?;
+
+    let result = py.allow_threads(|| {
+        blake2b::compress_with_last_node(
+            rounds,
+            &state,
+            &block,
+            counter,
+            final_block_flag,
+            last_node_flag,
+        )
+    });
+
+    Ok(PyBytes::new(py, &result.to_bytes()).into())
+}
+
+/// compress_into(out, rounds, starting_state, block, offset_counters, final_block_flag)
+/// --
+///
+/// Like ``compress`` except the result is written into the writable buffer
+/// `out` instead of a new ``bytes`` object.
+///
+/// Parameters
+/// ----------
+/// out : bytearray, memoryview, array
+///     A writable buffer of 64 bytes or of 8 64-bit words, such as
+///     ``array('Q')``, into which the resulting state is written.
+/// rounds : int
+///     The number of rounds of mixing to occur during hashing.
+/// starting_state : List[int], bytes
+///     A vector of 8 64-bit integers representing the starting state of the
+///     hash function.
+/// block : List[int], bytes
+///     A vector of 16 64-bit integers representing the message block to be hashed.
+/// offset_counters : List[int], bytes
+///     A vector of 2 64-bit integers representing the message byte offset at
+///     the end of the current block.
+/// final_block_flag : bool
+///     A flag indicating the final block of the message.
+#[pyfunction]
+fn compress_into(
+    py: Python,
+    out: buffer::StateBuffer,
+    rounds: usize,
+    starting_state: buffer::Words<u64>,
+    block: buffer::Words<u64>,
+    offset_counters: buffer::Words<u64>,
+    final_block_flag: bool,
+) -> PyResult<()> {
+    let (starting_state, block, counter) =
+        compress_args(&starting_state, &block, &offset_counters).map_err(compress_error)?;
+
+    let result = py.allow_threads(|| {
+        blake2b::compress(rounds, &starting_state, &block, counter, final_block_flag)
+    });
+
+    out.write(py, &result.0)
+}
+
+/// compress_in_place(state, rounds, block, offset_counters, final_block_flag)
+/// --
+///
+/// Like ``compress`` except the starting state is read from the writable
+/// buffer `state`, which is then overwritten with the resulting state.  This
+/// allows blocks to be chained without creating any objects.
+///
+/// Parameters
+/// ----------
+/// state : bytearray, memoryview, array
+///     A writable buffer of 64 bytes or of 8 64-bit words, such as
+///     ``array('Q')``, holding the state of the hash function.
+/// rounds : int
+///     The number of rounds of mixing to occur during hashing.
+/// block : List[int], bytes
+///     A vector of 16 64-bit integers representing the message block to be hashed.
+/// offset_counters : List[int], bytes
+///     A vector of 2 64-bit integers representing the message byte offset at
+///     the end of the current block.
+/// final_block_flag : bool
+///     A flag indicating the final block of the message.
+#[pyfunction]
+fn compress_in_place(
+    py: Python,
+    state: buffer::StateBuffer,
+    rounds: usize,
+    block: buffer::Words<u64>,
+    offset_counters: buffer::Words<u64>,
+    final_block_flag: bool,
+) -> PyResult<()> {
+    let starting_state = state.read(py)?;
+    let (starting_state, block, counter) =
+        compress_args(&starting_state, &block, &offset_counters).map_err(compress_error)?;
+
+    let result = py.allow_threads(|| {
+        blake2b::compress(rounds, &starting_state, &block, counter, final_block_flag)
+    });
+
+    state.write(py, &result.0)
+}
+
+/// compress_blocks(rounds, starting_state, blocks, offset, last_block_length)
+/// --
+///
+/// Run the blake2b compression function F on each 128-byte block in `blocks`
+/// in turn, as a hasher does.  The offset counters are advanced by 128 bytes
+/// for each block except the last, which adds `last_block_length` bytes and is
+/// the only block compressed with the final block flag set.  The GIL is
+/// released while compressing, after the blocks are copied unless they are
+/// given as ``bytes``.
+///
+/// Parameters
+/// ----------
+/// rounds : int
+///     The number of rounds of mixing to occur for each block.
+/// starting_state : List[int], bytes
+///     A vector of 8 64-bit integers representing the starting state of the
+///     hash function.
+/// blocks : bytes
+///     One or more 128-byte message blocks, with the last block padded with
+///     zeros.
+/// offset : int
+///     The 128-bit message byte offset at the start of the first block.
+/// last_block_length : int
+///     The number of message bytes in the last block, at most 128.
+///
+/// Returns
+/// -------
+/// out : bytes
+///     A vector of 64 bytes representing the state after the last block.
+#[pyfunction]
+fn compress_blocks(
+    py: Python,
+    rounds: usize,
+    starting_state: buffer::Words<u64>,
+    blocks: buffer::Bytes,
+    offset: u128,
+    last_block_length: usize,
+) -> PyResult<PyObject> {
+    // The blocks are only borrowed in place from immutable `bytes`, and any mutable buffer was
+    // copied when it was extracted, so they can't change while the GIL is released.
+    let result = py
+        .allow_threads(|| {
+            blake2b::compress_blocks(rounds, &starting_state, &blocks, offset, last_block_length)
+        })
+        .map_err(compress_error)?;
+
+    Ok(PyBytes::new(py, &result).into())
+}
+
+/// decode_and_compress(input, *, check_interval=None, timeout=None, max_rounds=None, progress=None)
+/// --
+///
+/// Calculates a blake2b hash for the tightly encoded input given in `input`,
+/// which may be a sequence of integers or any object supporting the buffer
+/// protocol.  The GIL is released while the rounds of mixing are done, so
+/// that other Python threads keep running during long computations.  Only
+/// ``bytes`` are read in place.  Any other buffer is copied first, so that it
+/// can't change while the GIL is released.  The keyword arguments are as for
+/// ``compress``.
+///
+/// Parameters
+/// ----------
+/// input : bytes, bytearray, memoryview, List[int]
+///     A vector of 213 bytes representing the tightly encoded input.
+///
+/// Returns
+/// -------
+/// out : bytes
+///     A vector of 64 bytes representing the blake2b hash of the input data.
+#[pyfunction]
+#[pyo3(signature = (input, *, check_interval=None, timeout=None, max_rounds=None, progress=None))]
+fn decode_and_compress(
+    py: Python,
+    input: buffer::Bytes,
+    check_interval: Option<usize>,
+    timeout: Option<f64>,
+    max_rounds: Option<usize>,
+    progress: Option<PyObject>,
+) -> PyResult<PyObject> {
+    let params = blake2b::decode_parameters(&input).map_err(compress_error)?;
+    let options = CompressOptions {
+        check_interval,
+        timeout,
+        max_rounds,
+        progress,
+    };
+    let result = options.compress(
+        py,
+        params.rounds,
+        &params.state,
+        &params.block,
+        &params.offset_counters,
+        params.final_block_flag,
+    )?;
+
+    Ok(PyBytes::new(py, &result).into())
+}
+
+/// Inputs to ``decode_and_compress_batch``, either as separate records or as a single buffer of
+/// concatenated records.
+#[derive(FromPyObject)]
+enum BatchInputs {
+    Buffer(buffer::Bytes),
+    Records(Vec<buffer::Bytes>),
+}
+
+/// decode_and_compress_batch(inputs, *, threads=None)
+/// --
+///
+/// Calculates blake2b hashes for many independent tightly encoded inputs
+/// across worker threads, with the GIL released.  Inputs with similar numbers
+/// of rounds are compressed together in SIMD lanes where the CPU supports it.
+///
+/// Parameters
+/// ----------
+/// inputs : List[bytes], bytes
+///     A list of 213-byte tightly encoded inputs, or a single buffer of
+///     concatenated 213-byte inputs.
+/// threads : int, optional
+///     The number of worker threads, which defaults to the available
+///     parallelism.
+///
+/// Returns
+/// -------
+/// out : List[Union[bytes, CompressError]]
+///     For each input in order, either a vector of 64 bytes representing the
+///     blake2b hash of the input data, or the error for an invalid input.
+#[pyfunction]
+#[pyo3(signature = (inputs, *, threads=None))]
+fn decode_and_compress_batch(
+    py: Python,
+    inputs: BatchInputs,
+    threads: Option<usize>,
+) -> PyResult<Vec<PyObject>> {
+    let threads = match threads {
+        Some(0) => return Err(PyValueError::new_err("threads must be at least 1")),
+        Some(threads) => threads,
+        None => std::thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+
+    let results = match &inputs {
+        BatchInputs::Buffer(buffer) => {
+            if buffer.len() % error::INPUT_LENGTH != 0 {
+                return Err(PyValueError::new_err(format!(
+                    "concatenated input length must be a multiple of {}, got: {}",
+                    error::INPUT_LENGTH,
+                    buffer.len(),
+                )));
+            }
+            let records: Vec<&[u8]> = buffer.chunks(error::INPUT_LENGTH).collect();
+            py.allow_threads(|| batch::decode_and_compress_batch(&records, threads))
+        }
+        BatchInputs::Records(records) => {
+            py.allow_threads(|| batch::decode_and_compress_batch(records, threads))
+        }
+    };
+
+    Ok(results
+        .into_iter()
+        .map(|result| match result {
+            Ok(output) => PyBytes::new(py, &output).into(),
+            Err(err) => compress_error(err).into_value(py).into(),
+        })
+        .collect())
+}
+
+/// blake2f_precompile(input, gas_limit)
+/// --
+///
+/// Runs the EIP-152 blake2 F precompile on the tightly encoded input given in
+/// the byte sequence `input`, charging one unit of gas per round.  The gas
+/// limit is checked before any rounds of mixing are done.
+///
+/// Parameters
+/// ----------
+/// input : bytes, List[int]
+///     A vector of 213 bytes representing the tightly encoded input.
+/// gas_limit : int
+///     The maximum amount of gas which may be used.
+///
+/// Returns
+/// -------
+/// out : (int, bytes)
+///     The gas used and a vector of 64 bytes representing the blake2b hash of
+///     the input data.
+///
+/// Raises
+/// ------
+/// OutOfGasError
+///     If the gas limit is less than the number of rounds in the input.
+/// ValueError
+///     If the input is not a valid tight encoding.
+#[pyfunction]
+fn blake2f_precompile(
+    py: Python,
+    input: buffer::Bytes,
+    gas_limit: u64,
+) -> PyResult<(u64, PyObject)> {
+    // Input of the right length is copied so that it can't change while the GIL is released, and
+    // input of any other length is rejected without any rounds of mixing.
+    let copied: Result<[u8; error::INPUT_LENGTH], _> = input[..].try_into();
+    let result = match copied {
+        Ok(input) => py.allow_threads(|| precompile::blake2f_precompile(&input, gas_limit)),
+        Err(_) => precompile::blake2f_precompile(&input, gas_limit),
+    };
+
+    match result {
+        Err(
+            err @ precompile::PrecompileError::OutOfGas {
+                gas_required,
+                gas_limit,
+            },
+        ) => Err(exception_with_fields(
+            OutOfGasError::new_err(err.to_string()),
+            &[("gas_required", gas_required), ("gas_limit", gas_limit)],
+        )),
+        Err(precompile::PrecompileError::InvalidInput(err)) => Err(compress_error(err)),
+        Ok((gas_used, output)) => Ok((gas_used, PyBytes::new(py, &output).into())),
+    }
+}
+
+/// Blake2FParams(rounds, state, block, offset_counters, final_block_flag)
+/// --
+///
+/// The parameters of the ``compress`` function, as decoded from the tightly
+/// packed encoding by ``decode_parameters``.  For compatibility with the tuple
+/// which ``decode_parameters`` used to return, instances may be unpacked,
+/// indexed and compared with tuples of the parameters in order.
+///
+/// Parameters
+/// ----------
+/// rounds : int
+///     The number of rounds of mixing to occur during hashing.
+/// state : List[int], bytes
+///     A vector of 8 64-bit integers representing the starting state of the
+///     hash function.
+/// block : List[int], bytes
+///     A vector of 16 64-bit integers representing the message block to be hashed.
+/// offset_counters : List[int], bytes
+///     A vector of 2 64-bit integers representing the message byte offset at
+///     the end of the current block.
+/// final_block_flag : bool
+///     A flag indicating the final block of the message.
+#[pyclass(name = "Blake2FParams", module = "blake2b", frozen)]
+struct PyBlake2FParams {
+    params: blake2b::Blake2FParams,
+}
+
+impl PyBlake2FParams {
+    fn to_tuple(&self) -> CompressArgs {
+        (
+            self.params.rounds,
+            self.params.state.to_vec(),
+            self.params.block.to_vec(),
+            self.params.offset_counters.to_vec(),
+            self.params.final_block_flag,
+        )
+    }
+}
+
+#[pymethods]
+impl PyBlake2FParams {
+    #[new]
+    fn new(
+        rounds: usize,
+        state: buffer::Words<u64>,
+        block: buffer::Words<u64>,
+        offset_counters: buffer::Words<u64>,
+        final_block_flag: bool,
+    ) -> PyResult<Self> {
+        error::check_vector_lengths(state.len(), block.len(), offset_counters.len())
+            .map_err(compress_error)?;
+
+        Ok(Self {
+            params: blake2b::Blake2FParams {
+                rounds,
+                state: state[..].try_into().unwrap(),
+                block: block[..].try_into().unwrap(),
+                offset_counters: offset_counters[..].try_into().unwrap(),
+                final_block_flag,
+            },
+        })
+    }
+
+    #[getter]
+    fn rounds(&self) -> usize {
+        self.params.rounds
+    }
+
+    #[getter]
+    fn state(&self) -> Vec<u64> {
+        self.params.state.to_vec()
+    }
+
+    #[getter]
+    fn block(&self) -> Vec<u64> {
+        self.params.block.to_vec()
+    }
+
+    #[getter]
+    fn offset_counters(&self) -> Vec<u64> {
+        self.params.offset_counters.to_vec()
+    }
+
+    #[getter]
+    fn final_block_flag(&self) -> bool {
+        self.params.final_block_flag
+    }
+
+    /// encode()
+    /// --
+    ///
+    /// Encode the parameters into the tightly packed encoding accepted by
+    /// ``decode_parameters``.
+    ///
+    /// Returns
+    /// -------
+    /// out : bytes
+    ///     A vector of 213 bytes representing the tightly encoded input.
+    fn encode(&self, py: Python) -> PyResult<PyObject> {
+        let encoded = self.params.encode().map_err(compress_error)?;
+
+        Ok(PyBytes::new(py, &encoded).into())
+    }
+
+    /// compress()
+    /// --
+    ///
+    /// Run the ``compress`` function on the parameters.  The GIL is released
+    /// while compressing.
+    ///
+    /// Returns
+    /// -------
+    /// out : bytes
+    ///     A vector of 64 bytes representing the blake2b hash of the input data.
+    fn compress(&self, py: Python) -> PyObject {
+        let result = py.allow_threads(|| self.params.compress());
+
+        PyBytes::new(py, &result).into()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Blake2FParams(rounds={}, state={:?}, block={:?}, offset_counters={:?}, final_block_flag={})",
+            self.params.rounds,
+            self.params.state,
+            self.params.block,
+            self.params.offset_counters,
+            if self.params.final_block_flag { "True" } else { "False" },
+        )
+    }
+
+    fn __richcmp__(&self, other: &Bound<'_, PyAny>, op: CompareOp) -> PyObject {
+        let py = other.py();
+        let equal = if let Ok(other) = other.downcast::<PyBlake2FParams>() {
+            self.params == other.get().params
+        } else if let Ok(other) = other.extract::<CompressArgs>() {
+            self.to_tuple() == other
+        } else {
+            return py.NotImplemented();
+        };
+
+        match op {
+            CompareOp::Eq => PyBool::new(py, equal).to_owned().into(),
+            CompareOp::Ne => PyBool::new(py, !equal).to_owned().into(),
+            _ => py.NotImplemented(),
+        }
+    }
+
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.params.hash(&mut hasher);
+
+        hasher.finish()
+    }
+
+    fn __len__(&self) -> usize {
+        5
+    }
+
+    fn __getitem__<'py>(
+        &self,
+        py: Python<'py>,
+        index: &Bound<'py, PyAny>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        self.to_tuple().into_pyobject(py)?.as_any().get_item(index)
+    }
+
+    fn __iter__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyIterator>> {
+        self.to_tuple().into_pyobject(py)?.as_any().try_iter()
+    }
+
+    fn __getnewargs__(&self) -> CompressArgs {
+        self.to_tuple()
+    }
+}
+
+/// Build blake2b parameters from the keyword arguments accepted by
+/// ``hashlib.blake2b``.
+#[allow(clippy::too_many_arguments)]
+fn hashlib_params(
+    digest_size: usize,
+    key_size: usize,
+    salt: &[u8],
+    person: &[u8],
+    fanout: usize,
+    depth: usize,
+    leaf_size: u64,
+    node_offset: u64,
+    node_depth: usize,
+    inner_size: usize,
+) -> params::Blake2bParams {
+    params::Blake2bParams::new()
+        .digest_length(digest_size)
+        .key_length(key_size)
+        .salt(salt)
+        .personal(person)
+        .fanout(fanout)
+        .depth(depth)
+        .leaf_length(leaf_size)
+        .node_offset(node_offset)
+        .node_depth(node_depth)
+        .inner_length(inner_size)
+}
+
+/// Blake2bParams(*, digest_size=64, key_size=0, salt=b'', person=b'', fanout=1, depth=1, leaf_size=0, node_offset=0, node_depth=0, inner_size=0)
+/// --
+///
+/// A validated blake2b parameter block, from which the starting state for the
+/// ``compress`` function is calculated.
+///
+/// Parameters
+/// ----------
+/// digest_size : int
+///     The number of bytes in the digest, between 1 and 64.
+/// key_size : int
+///     The number of bytes in the key, between 0 and 64.
+/// salt : bytes
+///     A salt of up to 16 bytes.
+/// person : bytes
+///     A personalization string of up to 16 bytes.
+/// fanout : int
+///     The tree hashing fanout, between 0 and 255.
+/// depth : int
+///     The maximal tree hashing depth, between 1 and 255.
+/// leaf_size : int
+///     The tree hashing leaf size, between 0 and 2 ** 32 - 1.
+/// node_offset : int
+///     The tree hashing node offset, between 0 and 2 ** 64 - 1.
+/// node_depth : int
+///     The tree hashing node depth, between 0 and 255.
+/// inner_size : int
+///     The tree hashing inner hash length, between 0 and 64.
+#[pyclass(name = "Blake2bParams", frozen)]
+struct PyBlake2bParams {
+    params: params::Blake2bParams,
+}
+
+#[pymethods]
+impl PyBlake2bParams {
+    #[new]
+    #[pyo3(signature = (
+        *,
+        digest_size=64,
+        key_size=0,
+        salt=buffer::Bytes::default(),
+        person=buffer::Bytes::default(),
+        fanout=1,
+        depth=1,
+        leaf_size=0,
+        node_offset=0,
+        node_depth=0,
+        inner_size=0,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        digest_size: usize,
+        key_size: usize,
+        salt: buffer::Bytes,
+        person: buffer::Bytes,
+        fanout: usize,
+        depth: usize,
+        leaf_size: u64,
+        node_offset: u64,
+        node_depth: usize,
+        inner_size: usize,
+    ) -> PyResult<Self> {
+        let params = hashlib_params(
+            digest_size,
+            key_size,
+            &salt,
+            &person,
+            fanout,
+            depth,
+            leaf_size,
+            node_offset,
+            node_depth,
+            inner_size,
+        );
+        params.validate().map_err(PyValueError::new_err)?;
+
+        Ok(Self { params })
+    }
+
+    /// parameter_block()
+    /// --
+    ///
+    /// Encode the parameter block.
+    ///
+    /// Returns
+    /// -------
+    /// out : bytes
+    ///     A vector of 64 bytes representing the parameter block.
+    fn parameter_block(&self, py: Python) -> PyResult<PyObject> {
+        let parameter_block = self.params.to_bytes().map_err(PyValueError::new_err)?;
+
+        Ok(PyBytes::new(py, &parameter_block).into())
+    }
+
+    /// starting_state()
+    /// --
+    ///
+    /// Calculate the starting state for the ``compress`` function by XORing
+    /// the parameter block into the initialization vector.
+    ///
+    /// Returns
+    /// -------
+    /// out : List[int]
+    ///     A vector of 8 64-bit integers representing the starting state.
+    fn starting_state(&self) -> PyResult<Vec<u64>> {
+        let starting_state = self
+            .params
+            .starting_state()
+            .map_err(PyValueError::new_err)?;
+
+        Ok(starting_state.to_vec())
+    }
+}
+
+/// Blake2b(data=None, *, digest_size=64, key=b'', salt=b'', person=b'', fanout=1, depth=1, leaf_size=0, node_offset=0, node_depth=0, inner_size=0, last_node=False, usedforsecurity=True, rounds=12)
+/// --
+///
+/// A streaming blake2b hasher with the same interface as ``hashlib.blake2b``.
+/// Message blocks are padded, the message byte offset is tracked and the
+/// final block flag is set before calling ``compress``.
+///
+/// Parameters
+/// ----------
+/// data : bytes, optional
+///     Initial message bytes to feed into the hasher.
+/// digest_size : int
+///     The number of bytes in the digest, between 1 and 64.
+/// key : bytes
+///     A key of up to 64 bytes for keyed hashing.
+/// salt : bytes
+///     A salt of up to 16 bytes.
+/// person : bytes
+///     A personalization string of up to 16 bytes.
+/// fanout : int
+///     The tree hashing fanout, between 0 and 255.
+/// depth : int
+///     The maximal tree hashing depth, between 1 and 255.
+/// leaf_size : int
+///     The tree hashing leaf size, between 0 and 2 ** 32 - 1.
+/// node_offset : int
+///     The tree hashing node offset, between 0 and 2 ** 64 - 1.
+/// node_depth : int
+///     The tree hashing node depth, between 0 and 255.
+/// inner_size : int
+///     The tree hashing inner hash length, between 0 and 64.
+/// last_node : bool
+///     A flag indicating the last node at a tree hashing level.
+/// usedforsecurity : bool
+///     Accepted for compatibility with ``hashlib`` and otherwise ignored.
+/// rounds : int
+///     The number of rounds of mixing passed to ``compress`` for each block.
+///     Standard blake2b uses 12 rounds.
+#[pyclass(name = "Blake2b", module = "blake2b")]
+#[derive(Clone)]
+struct PyBlake2b {
+    hasher: hasher::Blake2b,
+}
+
+#[pymethods]
+impl PyBlake2b {
+    #[classattr]
+    const SALT_SIZE: usize = params::SALTBYTES;
+    #[classattr]
+    const PERSON_SIZE: usize = params::PERSONALBYTES;
+    #[classattr]
+    const MAX_KEY_SIZE: usize = hasher::KEYBYTES;
+    #[classattr]
+    const MAX_DIGEST_SIZE: usize = hasher::OUTBYTES;
+
+    #[new]
+    #[pyo3(signature = (
+        data=None,
+        *,
+        digest_size=64,
+        key=buffer::Bytes::default(),
+        salt=buffer::Bytes::default(),
+        person=buffer::Bytes::default(),
+        fanout=1,
+        depth=1,
+        leaf_size=0,
+        node_offset=0,
+        node_depth=0,
+        inner_size=0,
+        last_node=false,
+        usedforsecurity=true,
+        rounds=hasher::ROUNDS,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        data: Option<buffer::Bytes>,
+        digest_size: usize,
+        key: buffer::Bytes,
+        salt: buffer::Bytes,
+        person: buffer::Bytes,
+        fanout: usize,
+        depth: usize,
+        leaf_size: u64,
+        node_offset: u64,
+        node_depth: usize,
+        inner_size: usize,
+        last_node: bool,
+        usedforsecurity: bool,
+        rounds: usize,
+    ) -> PyResult<Self> {
+        let _ = usedforsecurity;
+
+        let params = hashlib_params(
+            digest_size,
+            key.len(),
+            &salt,
+            &person,
+            fanout,
+            depth,
+            leaf_size,
+            node_offset,
+            node_depth,
+            inner_size,
+        )
+        .last_node(last_node);
+
+        let mut hasher = hasher::Blake2b::with_params_and_rounds(&params, &key, rounds)
+            .map_err(PyValueError::new_err)?;
+        if let Some(data) = data {
+            hasher.update(&data);
+        }
+
+        Ok(Self { hasher })
+    }
+
+    /// The canonical name of this hash.
+    #[getter]
+    fn name(&self) -> &'static str {
+        "blake2b"
+    }
+
+    /// The number of bytes in the digest produced by this hasher.
+    #[getter]
+    fn digest_size(&self) -> usize {
+        self.hasher.digest_size()
+    }
+
+    /// The internal block size of the hash algorithm in bytes.
+    #[getter]
+    fn block_size(&self) -> usize {
+        hasher::BLOCKBYTES
+    }
+
+    /// update(data)
+    /// --
+    ///
+    /// Feed the bytes in `data` into the hasher.
+    ///
+    /// Parameters
+    /// ----------
+    /// data : bytes, List[int]
+    ///     Message bytes to be hashed.
+    fn update(&mut self, data: buffer::Bytes) {
+        self.hasher.update(&data);
+    }
+
+    /// digest()
+    /// --
+    ///
+    /// Return the digest of all bytes fed into the hasher so far.
+    ///
+    /// Returns
+    /// -------
+    /// out : bytes
+    ///     A vector of ``digest_size`` bytes representing the blake2b hash of
+    ///     the message.
+    fn digest(&self, py: Python) -> PyObject {
+        PyBytes::new(py, &self.hasher.finalize()).into()
+    }
+
+    /// hexdigest()
+    /// --
+    ///
+    /// Like ``digest`` except the digest is returned as a string of
+    /// hexadecimal digits.
+    ///
+    /// Returns
+    /// -------
+    /// out : str
+    ///     A string of ``2 * digest_size`` hexadecimal digits.
+    fn hexdigest(&self) -> String {
+        self.hasher
+            .finalize()
+            .iter()
+            .map(|x| format!("{:02x}", x))
+            .collect()
+    }
+
+    /// verify(tag)
+    /// --
+    ///
+    /// Check whether `tag` matches the digest of all bytes fed into the hasher
+    /// so far, typically a MAC produced by a hasher with the same ``key``.  A
+    /// truncated tag is compared against the leading bytes of the digest.  The
+    /// comparison is done in constant time.
+    ///
+    /// Parameters
+    /// ----------
+    /// tag : bytes
+    ///     A tag of between 1 and ``digest_size`` bytes.
+    ///
+    /// Returns
+    /// -------
+    /// out : bool
+    ///     ``True`` if the tag matches, ``False`` otherwise.
+    fn verify(&self, tag: buffer::Bytes) -> bool {
+        self.hasher.verify(&tag)
+    }
+
+    /// copy()
+    /// --
+    ///
+    /// Return a copy of the hasher, which may be updated independently.
+    ///
+    /// Returns
+    /// -------
+    /// out : Blake2b
+    ///     A copy of the hasher.
+    fn copy(&self) -> Self {
+        self.clone()
+    }
+
+    /// __getstate__()
+    /// --
+    ///
+    /// Return the state of the hasher, so that hashing can be resumed later,
+    /// possibly in another process.  This supports ``pickle`` and ``copy``.
+    ///
+    /// The state is 220 bytes in a versioned binary format which holds the
+    /// chain value, the byte counter, the buffered partial block and the
+    /// parameters.  Until more than a block of message has been fed into a
+    /// keyed hasher, the buffered block is the key, so the state must be kept
+    /// as secret as the key.
+    ///
+    /// Returns
+    /// -------
+    /// out : bytes
+    ///     The serialized state of the hasher.
+    fn __getstate__(&self, py: Python) -> PyObject {
+        PyBytes::new(py, &self.hasher.to_state_bytes()).into()
+    }
+
+    /// __setstate__(state)
+    /// --
+    ///
+    /// Restore the state of the hasher from one returned by ``__getstate__``.
+    ///
+    /// Parameters
+    /// ----------
+    /// state : bytes
+    ///     The serialized state of a hasher.
+    fn __setstate__(&mut self, state: buffer::Bytes) -> PyResult<()> {
+        self.hasher = hasher::Blake2b::from_state_bytes(&state).map_err(PyValueError::new_err)?;
+
+        Ok(())
+    }
+}
+
+/// backend()
+/// --
+///
+/// Return the name of the implementation of the rounds of mixing used by the
+/// ``compress`` functions, which is chosen at runtime as the fastest one that
+/// the CPU supports.
+///
+/// Returns
+/// -------
+/// out : str
+///     One of ``"avx2"``, ``"sse4.1"`` or ``"scalar"``.
+#[pyfunction(name = "backend")]
+fn py_backend() -> &'static str {
+    backend::backend().name()
+}
+
+/// blake2s_compress(rounds, starting_state, block, offset_counters, final_block_flag)
+/// --
+///
+/// Calculates a blake2s hash for the given message block.
+///
+/// Parameters
+/// ----------
+/// rounds : int
+///     The number of rounds of mixing to occur during hashing.  Standard
+///     blake2s uses 10 rounds.
+/// starting_state : List[int], bytes
+///     A vector of 8 32-bit integers representing the starting state of the
+///     hash function.
+/// block : List[int], bytes
+///     A vector of 16 32-bit integers representing the message block to be hashed.
+/// offset_counters : List[int], bytes
+///     A vector of 2 32-bit integers representing the message byte offset at
+///     the end of the current block.
+/// final_block_flag : bool
+///     A flag indicating the final block of the message.
+///
+/// Returns
+/// -------
+/// out : bytes
+///     A vector of 32 bytes representing the blake2s hash of the input data.
+#[pyfunction]
+fn blake2s_compress(
+    py: Python,
+    rounds: usize,
+    starting_state: buffer::Words<u32>,
+    block: buffer::Words<u32>,
+    offset_counters: buffer::Words<u32>,
+    final_block_flag: bool,
+) -> PyResult<PyObject> {
+    error::check_vector_lengths(starting_state.len(), block.len(), offset_counters.len())
+        .map_err(compress_error)?;
+    // Copy the vectors so that they can't change while the GIL is released.
+    let starting_state: [u32; 8] = starting_state[..].try_into().unwrap();
+    let block: [u32; 16] = block[..].try_into().unwrap();
+    let offset_counters: [u32; 2] = offset_counters[..].try_into().unwrap();
+
+    let result = py.allow_threads(|| {
+        blake2s::F(
+            rounds,
+            &starting_state,
+            &block,
+            &offset_counters,
+            final_block_flag,
+        )
+    });
+
+    match result {
+        Err(err) => Err(compress_error(err)),
+        Ok(ok) => Ok(PyBytes::new(py, &ok).into()),
+    }
+}
+
+/// Blake2s(data=None, *, digest_size=32, key=b'', salt=b'', person=b'', fanout=1, depth=1, leaf_size=0, node_offset=0, node_depth=0, inner_size=0, last_node=False, usedforsecurity=True, rounds=10)
+/// --
+///
+/// A streaming blake2s hasher with the same interface as ``hashlib.blake2s``.
+///
+/// Parameters
+/// ----------
+/// data : bytes, optional
+///     Initial message bytes to feed into the hasher.
+/// digest_size : int
+///     The number of bytes in the digest, between 1 and 32.
+/// key : bytes
+///     A key of up to 32 bytes for keyed hashing.
+/// salt : bytes
+///     A salt of up to 8 bytes.
+/// person : bytes
+///     A personalization string of up to 8 bytes.
+/// fanout : int
+///     The tree hashing fanout, between 0 and 255.
+/// depth : int
+///     The maximal tree hashing depth, between 1 and 255.
+/// leaf_size : int
+///     The tree hashing leaf size, between 0 and 2 ** 32 - 1.
+/// node_offset : int
+///     The tree hashing node offset, between 0 and 2 ** 48 - 1.
+/// node_depth : int
+///     The tree hashing node depth, between 0 and 255.
+/// inner_size : int
+///     The tree hashing inner hash length, between 0 and 32.
+/// last_node : bool
+///     A flag indicating the last node at a tree hashing level.
+/// usedforsecurity : bool
+///     Accepted for compatibility with ``hashlib`` and otherwise ignored.
+/// rounds : int
+///     The number of rounds of mixing passed to ``blake2s_compress`` for each
+///     block.  Standard blake2s uses 10 rounds.
+#[pyclass(name = "Blake2s")]
+#[derive(Clone)]
+struct PyBlake2s {
+    hasher: blake2s::Blake2s,
+}
+
+#[pymethods]
+impl PyBlake2s {
+    #[classattr]
+    const SALT_SIZE: usize = blake2s::SALTBYTES;
+    #[classattr]
+    const PERSON_SIZE: usize = blake2s::PERSONALBYTES;
+    #[classattr]
+    const MAX_KEY_SIZE: usize = blake2s::KEYBYTES;
+    #[classattr]
+    const MAX_DIGEST_SIZE: usize = blake2s::OUTBYTES;
+
+    #[new]
+    #[pyo3(signature = (
+        data=None,
+        *,
+        digest_size=32,
+        key=buffer::Bytes::default(),
+        salt=buffer::Bytes::default(),
+        person=buffer::Bytes::default(),
+        fanout=1,
+        depth=1,
+        leaf_size=0,
+        node_offset=0,
+        node_depth=0,
+        inner_size=0,
+        last_node=false,
+        usedforsecurity=true,
+        rounds=blake2s::ROUNDS,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        data: Option<buffer::Bytes>,
+        digest_size: usize,
+        key: buffer::Bytes,
+        salt: buffer::Bytes,
+        person: buffer::Bytes,
+        fanout: usize,
+        depth: usize,
+        leaf_size: u64,
+        node_offset: u64,
+        node_depth: usize,
+        inner_size: usize,
+        last_node: bool,
+        usedforsecurity: bool,
+        rounds: usize,
+    ) -> PyResult<Self> {
+        let _ = usedforsecurity;
+
+        let params = blake2s::Blake2sParams::new()
+            .digest_length(digest_size)
+            .key_length(key.len())
+            .salt(&salt)
+            .personal(&person)
+            .fanout(fanout)
+            .depth(depth)
+            .leaf_length(leaf_size)
+            .node_offset(node_offset)
+            .node_depth(node_depth)
+            .inner_length(inner_size)
+            .last_node(last_node);
+
+        let mut hasher = blake2s::Blake2s::with_params_and_rounds(&params, &key, rounds)
+            .map_err(PyValueError::new_err)?;
+        if let Some(data) = data {
+            hasher.update(&data);
+        }
+
+        Ok(Self { hasher })
+    }
+
+    /// The canonical name of this hash.
+    #[getter]
+    fn name(&self) -> &'static str {
+        "blake2s"
+    }
+
+    /// The number of bytes in the digest produced by this hasher.
+    #[getter]
+    fn digest_size(&self) -> usize {
+        self.hasher.digest_size()
+    }
+
+    /// The internal block size of the hash algorithm in bytes.
+    #[getter]
+    fn block_size(&self) -> usize {
+        blake2s::BLOCKBYTES
+    }
+
+    /// update(data)
+    /// --
+    ///
+    /// Feed the bytes in `data` into the hasher.
+    ///
+    /// Parameters
+    /// ----------
+    /// data : bytes, List[int]
+    ///     Message bytes to be hashed.
+    fn update(&mut self, data: buffer::Bytes) {
+        self.hasher.update(&data);
+    }
+
+    /// digest()
+    /// --
+    ///
+    /// Return the digest of all bytes fed into the hasher so far.
+    ///
+    /// Returns
+    /// -------
+    /// out : bytes
+    ///     A vector of ``digest_size`` bytes representing the blake2s hash of
+    ///     the message.
+    fn digest(&self, py: Python) -> PyObject {
+        PyBytes::new(py, &self.hasher.finalize()).into()
+    }
+
+    /// hexdigest()
+    /// --
+    ///
+    /// Like ``digest`` except the digest is returned as a string of
+    /// hexadecimal digits.
+    ///
+    /// Returns
+    /// -------
+    /// out : str
+    ///     A string of ``2 * digest_size`` hexadecimal digits.
+    fn hexdigest(&self) -> String {
+        self.hasher
+            .finalize()
+            .iter()
+            .map(|x| format!("{:02x}", x))
+            .collect()
+    }
+
+    /// copy()
+    /// --
+    ///
+    /// Return a copy of the hasher, which may be updated independently.
+    ///
+    /// Returns
+    /// -------
+    /// out : Blake2s
+    ///     A copy of the hasher.
+    fn copy(&self) -> Self {
+        self.clone()
+    }
+}
+
+/// Blake2bp(data=None, *, digest_size=64, key=b'')
+/// --
+///
+/// A streaming BLAKE2bp hasher, which hashes message blocks with four
+/// parallel blake2b leaves and combines their outputs with a root node.
+/// Large updates hash each leaf on its own thread.
+///
+/// Parameters
+/// ----------
+/// data : bytes, optional
+///     Initial message bytes to feed into the hasher.
+/// digest_size : int
+///     The number of bytes in the digest, between 1 and 64.
+/// key : bytes
+///     A key of up to 64 bytes for keyed hashing.
+#[pyclass(name = "Blake2bp")]
+#[derive(Clone)]
+struct PyBlake2bp {
+    hasher: blake2bp::Blake2bp,
+}
+
+#[pymethods]
+impl PyBlake2bp {
+    #[new]
+    #[pyo3(signature = (data=None, *, digest_size=64, key=buffer::Bytes::default()))]
+    fn new(data: Option<buffer::Bytes>, digest_size: usize, key: buffer::Bytes) -> PyResult<Self> {
+        let result = if key.is_empty() {
+            blake2bp::Blake2bp::with_digest_size(digest_size)
+        } else {
+            blake2bp::Blake2bp::new_keyed(&key, digest_size)
+        };
+
+        let mut hasher = result.map_err(PyValueError::new_err)?;
+        if let Some(data) = data {
+            hasher.update(&data);
+        }
+
+        Ok(Self { hasher })
+    }
+
+    /// The canonical name of this hash.
+    #[getter]
+    fn name(&self) -> &'static str {
+        "blake2bp"
+    }
+
+    /// The number of bytes in the digest produced by this hasher.
+    #[getter]
+    fn digest_size(&self) -> usize {
+        self.hasher.digest_size()
+    }
+
+    /// The internal block size of the hash algorithm in bytes.
+    #[getter]
+    fn block_size(&self) -> usize {
+        hasher::BLOCKBYTES
+    }
+
+    /// update(data)
+    /// --
+    ///
+    /// Feed the bytes in `data` into the hasher.
+    ///
+    /// Parameters
+    /// ----------
+    /// data : bytes, List[int]
+    ///     Message bytes to be hashed.
+    fn update(&mut self, data: buffer::Bytes) {
+        self.hasher.update(&data);
+    }
+
+    /// digest()
+    /// --
+    ///
+    /// Return the digest of all bytes fed into the hasher so far.
+    ///
+    /// Returns
+    /// -------
+    /// out : bytes
+    ///     A vector of ``digest_size`` bytes representing the BLAKE2bp hash of
+    ///     the message.
+    fn digest(&self, py: Python) -> PyObject {
+        PyBytes::new(py, &self.hasher.finalize()).into()
+    }
+
+    /// hexdigest()
+    /// --
+    ///
+    /// Like ``digest`` except the digest is returned as a string of
+    /// hexadecimal digits.
+    ///
+    /// Returns
+    /// -------
+    /// out : str
+    ///     A string of ``2 * digest_size`` hexadecimal digits.
+    fn hexdigest(&self) -> String {
+        self.hasher
+            .finalize()
+            .iter()
+            .map(|x| format!("{:02x}", x))
+            .collect()
+    }
+
+    /// copy()
+    /// --
+    ///
+    /// Return a copy of the hasher, which may be updated independently.
+    ///
+    /// Returns
+    /// -------
+    /// out : Blake2bp
+    ///     A copy of the hasher.
+    fn copy(&self) -> Self {
+        self.clone()
+    }
+}
+
+/// Blake2xb(data=None, *, length=None, key=b'', salt=b'', person=b'')
+/// --
+///
+/// A streaming BLAKE2Xb hasher, which produces output of any length by
+/// expanding a blake2b root hash.
+///
+/// Parameters
+/// ----------
+/// data : bytes, optional
+///     Initial message bytes to feed into the hasher.
+/// length : int, optional
+///     The number of output bytes, between 1 and 2 ** 32 - 2.  If omitted,
+///     the output length is unknown in advance.
+/// key : bytes
+///     A key of up to 64 bytes for keyed hashing.
+/// salt : bytes
+///     A salt of up to 16 bytes.
+/// person : bytes
+///     A personalization string of up to 16 bytes.
+#[pyclass(name = "Blake2xb")]
+#[derive(Clone)]
+struct PyBlake2xb {
+    hasher: blake2x::Blake2xb,
+    reader: Option<blake2x::Blake2xbReader>,
+}
+
+/// Allocate a zeroed output buffer of `length` bytes, raising ``MemoryError`` rather than aborting
+/// the process if the allocation fails.  Output of unknown length may be up to 256 GiB.
+fn zeroed_output(length: usize) -> PyResult<Vec<u8>> {
+    let mut out = Vec::new();
+    out.try_reserve_exact(length).map_err(|_| {
+        PyMemoryError::new_err(format!("cannot allocate {} bytes of output", length))
+    })?;
+    out.resize(length, 0);
+
+    Ok(out)
+}
+
+impl PyBlake2xb {
+    fn checked_finalize(&self, length: Option<usize>) -> PyResult<Vec<u8>> {
+        let length = match (length, self.hasher.output_length()) {
+            (Some(length), _) => length,
+            (None, Some(output_length)) => output_length as usize,
+            (None, None) => {
+                return Err(PyValueError::new_err(
+                    "length must be given when the output length is unknown",
+                ));
+            }
+        };
+
+        self.hasher
+            .check_finalize_length(length)
+            .map_err(PyValueError::new_err)?;
+        let mut out = zeroed_output(length)?;
+        self.hasher
+            .finalize_into(&mut out)
+            .map_err(PyValueError::new_err)?;
+
+        Ok(out)
+    }
+}
+
+#[pymethods]
+impl PyBlake2xb {
+    #[new]
+    #[pyo3(signature = (data=None, *, length=None, key=buffer::Bytes::default(), salt=buffer::Bytes::default(), person=buffer::Bytes::default()))]
+    fn new(
+        data: Option<buffer::Bytes>,
+        length: Option<u32>,
+        key: buffer::Bytes,
+        salt: buffer::Bytes,
+        person: buffer::Bytes,
+    ) -> PyResult<Self> {
+        let mut hasher = blake2x::Blake2xb::with_options(length, &key, &salt, &person)
+            .map_err(PyValueError::new_err)?;
+        if let Some(data) = data {
+            hasher.update(&data);
+        }
+
+        Ok(Self {
+            hasher,
+            reader: None,
+        })
+    }
+
+    /// The canonical name of this hash.
+    #[getter]
+    fn name(&self) -> &'static str {
+        "blake2xb"
+    }
+
+    /// The number of output bytes, or ``None`` if it is unknown.
+    #[getter]
+    fn length(&self) -> Option<u32> {
+        self.hasher.output_length()
+    }
+
+    /// The internal block size of the hash algorithm in bytes.
+    #[getter]
+    fn block_size(&self) -> usize {
+        hasher::BLOCKBYTES
+    }
+
+    /// update(data)
+    /// --
+    ///
+    /// Feed the bytes in `data` into the hasher.  This may not be called once
+    /// output has been read with ``read``.
+    ///
+    /// Parameters
+    /// ----------
+    /// data : bytes, List[int]
+    ///     Message bytes to be hashed.
+    fn update(&mut self, data: buffer::Bytes) -> PyResult<()> {
+        if self.reader.is_some() {
+            return Err(PyValueError::new_err(
+                "cannot update hasher after output has been read",
+            ));
+        }
+        self.hasher.update(&data);
+
+        Ok(())
+    }
+
+    /// read(n)
+    /// --
+    ///
+    /// Read the next `n` bytes of output.  The first call finalizes the hash.
+    /// If the output length is unknown, every output block is a full 64-byte
+    /// expansion hash.
+    ///
+    /// Parameters
+    /// ----------
+    /// n : int
+    ///     The number of bytes to read.
+    ///
+    /// Returns
+    /// -------
+    /// out : bytes
+    ///     The next `n` bytes of output, or fewer if the end of the output is
+    ///     reached.
+    fn read(&mut self, py: Python, n: usize) -> PyResult<PyObject> {
+        let hasher = &self.hasher;
+        let reader = self.reader.get_or_insert_with(|| hasher.finalize_xof());
+
+        // Never allocate more than the output that remains, which is bounded by the length given
+        // to the constructor when it is known.
+        let remaining = reader.output_length() - reader.position();
+        let mut out = zeroed_output(n.min(usize::try_from(remaining).unwrap_or(usize::MAX)))?;
+        let written = reader.read(&mut out);
+
+        Ok(PyBytes::new(py, &out[..written]).into())
+    }
+
+    /// digest(length=None)
+    /// --
+    ///
+    /// Return the whole output for all bytes fed into the hasher so far.
+    ///
+    /// Parameters
+    /// ----------
+    /// length : int, optional
+    ///     The number of output bytes.  This must match the ``length`` given
+    ///     to the constructor, if any, and is required otherwise.
+    ///
+    /// Returns
+    /// -------
+    /// out : bytes
+    ///     A vector of `length` bytes of BLAKE2Xb output.
+    #[pyo3(signature = (length=None))]
+    fn digest(&self, py: Python, length: Option<usize>) -> PyResult<PyObject> {
+        Ok(PyBytes::new(py, &self.checked_finalize(length)?).into())
+    }
+
+    /// hexdigest(length=None)
+    /// --
+    ///
+    /// Like ``digest`` except the output is returned as a string of
+    /// hexadecimal digits.
+    ///
+    /// Returns
+    /// -------
+    /// out : str
+    ///     A string of ``2 * length`` hexadecimal digits.
+    #[pyo3(signature = (length=None))]
+    fn hexdigest(&self, length: Option<usize>) -> PyResult<String> {
+        Ok(self
+            .checked_finalize(length)?
+            .iter()
+            .map(|x| format!("{:02x}", x))
+            .collect())
+    }
+
+    /// copy()
+    /// --
+    ///
+    /// Return a copy of the hasher, including the position of any output
+    /// being read.
+    ///
+    /// Returns
+    /// -------
+    /// out : Blake2xb
+    ///     A copy of the hasher.
+    fn copy(&self) -> Self {
+        self.clone()
+    }
+}
+
+/// Blake2bTree(*, fanout=2, depth=255, leaf_size=4096, inner_size=64, digest_size=64, key=b'', salt=b'', person=b'')
+/// --
+///
+/// A blake2b tree hash, which splits the message into leaves of
+/// ``leaf_size`` bytes and hashes the inner hashes of up to ``fanout`` nodes
+/// at each level above until a single root node remains.  Leaves of large
+/// messages are hashed on separate threads.
+///
+/// Parameters
+/// ----------
+/// fanout : int
+///     The tree hashing fanout, between 0 (unlimited) and 255.
+/// depth : int
+///     The maximal tree hashing depth, between 1 and 255.
+/// leaf_size : int
+///     The tree hashing leaf size, between 0 (unlimited) and 2 ** 32 - 1.
+/// inner_size : int
+///     The inner hash length, between 1 and 64.
+/// digest_size : int
+///     The number of bytes in the digest, between 1 and 64.
+/// key : bytes
+///     A key of up to 64 bytes with which every leaf is keyed.
+/// salt : bytes
+///     A salt of up to 16 bytes.
+/// person : bytes
+///     A personalization string of up to 16 bytes.
+#[pyclass(name = "Blake2bTree", frozen)]
+struct PyBlake2bTree {
+    tree: tree::Blake2bTree,
+}
+
+#[pymethods]
+impl PyBlake2bTree {
+    #[new]
+    #[pyo3(signature = (
+        *,
+        fanout=2,
+        depth=255,
+        leaf_size=4096,
+        inner_size=64,
+        digest_size=64,
+        key=buffer::Bytes::default(),
+        salt=buffer::Bytes::default(),
+        person=buffer::Bytes::default(),
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        fanout: usize,
+        depth: usize,
+        leaf_size: u64,
+        inner_size: usize,
+        digest_size: usize,
+        key: buffer::Bytes,
+        salt: buffer::Bytes,
+        person: buffer::Bytes,
+    ) -> PyResult<Self> {
+        let params = hashlib_params(
+            digest_size,
+            key.len(),
+            &salt,
+            &person,
+            fanout,
+            depth,
+            leaf_size,
+            0,
+            0,
+            inner_size,
+        );
+        let tree = tree::Blake2bTree::new(&params, &key).map_err(PyValueError::new_err)?;
+
+        Ok(Self { tree })
+    }
+
+    /// hash(data)
+    /// --
+    ///
+    /// Calculate the tree hash digest of `data`.
+    ///
+    /// Parameters
+    /// ----------
+    /// data : bytes, List[int]
+    ///     Message bytes to be hashed.
+    ///
+    /// Returns
+    /// -------
+    /// out : bytes
+    ///     A vector of ``digest_size`` bytes representing the tree hash of the
+    ///     message.
+    fn hash(&self, py: Python, data: buffer::Bytes) -> PyResult<PyObject> {
+        let digest = self.tree.hash(&data).map_err(PyValueError::new_err)?;
+
+        Ok(PyBytes::new(py, &digest).into())
+    }
+
+    /// hash_leaf(index, data, last_node)
+    /// --
+    ///
+    /// Calculate the inner hash of a single leaf, so that the digest of a
+    /// large message can be updated by rehashing only the leaves which change.
+    ///
+    /// Parameters
+    /// ----------
+    /// index : int
+    ///     The offset of the leaf in the message, counted in leaves.
+    /// data : bytes, List[int]
+    ///     The leaf bytes, of at most ``leaf_size`` bytes.
+    /// last_node : bool
+    ///     Whether this is the last leaf of the message.
+    ///
+    /// Returns
+    /// -------
+    /// out : bytes
+    ///     A vector of ``inner_size`` bytes.
+    fn hash_leaf(
+        &self,
+        py: Python,
+        index: u64,
+        data: buffer::Bytes,
+        last_node: bool,
+    ) -> PyResult<PyObject> {
+        let leaf_hash = self
+            .tree
+            .hash_leaf(index, &data, last_node)
+            .map_err(PyValueError::new_err)?;
+
+        Ok(PyBytes::new(py, &leaf_hash).into())
+    }
+
+    /// hash_from_leaves(leaf_hashes)
+    /// --
+    ///
+    /// Calculate the tree hash digest from the inner hashes of all leaves, as
+    /// returned by ``hash_leaf``.
+    ///
+    /// Parameters
+    /// ----------
+    /// leaf_hashes : List[bytes]
+    ///     The inner hashes of all leaves, in order.
+    ///
+    /// Returns
+    /// -------
+    /// out : bytes
+    ///     A vector of ``digest_size`` bytes representing the tree hash of the
+    ///     message.
+    fn hash_from_leaves(&self, py: Python, leaf_hashes: Vec<buffer::Bytes>) -> PyResult<PyObject> {
+        let digest = self
+            .tree
+            .hash_from_leaves(&leaf_hashes)
+            .map_err(PyValueError::new_err)?;
+
+        Ok(PyBytes::new(py, &digest).into())
+    }
+}
+
+/// Functions for calculating blake2b hashes.
+#[pymodule(name = "blake2b")]
+fn blake2b_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(decode_parameters, m)?)?;
+    m.add_class::<PyBlake2FParams>()?;
+    m.add_function(wrap_pyfunction!(encode_parameters, m)?)?;
+    m.add_function(wrap_pyfunction!(compress, m)?)?;
+    m.add_function(wrap_pyfunction!(compress_with_last_node, m)?)?;
+    m.add_function(wrap_pyfunction!(compress_into, m)?)?;
+    m.add_function(wrap_pyfunction!(compress_in_place, m)?)?;
+    m.add_function(wrap_pyfunction!(compress_blocks, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_and_compress, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_and_compress_batch, m)?)?;
+    m.add_function(wrap_pyfunction!(blake2f_precompile, m)?)?;
+    m.add_function(wrap_pyfunction!(py_backend, m)?)?;
+    m.add("CompressError", m.py().get_type::<CompressError>())?;
+    m.add(
+        "InvalidInputLengthError",
+        m.py().get_type::<InvalidInputLengthError>(),
+    )?;
+    m.add(
+        "InvalidFinalBlockFlagError",
+        m.py().get_type::<InvalidFinalBlockFlagError>(),
+    )?;
+    m.add(
+        "InvalidStartingStateLengthError",
+        m.py().get_type::<InvalidStartingStateLengthError>(),
+    )?;
+    m.add(
+        "InvalidBlockLengthError",
+        m.py().get_type::<InvalidBlockLengthError>(),
+    )?;
+    m.add(
+        "InvalidOffsetCountersLengthError",
+        m.py().get_type::<InvalidOffsetCountersLengthError>(),
+    )?;
+    m.add(
+        "InvalidRoundsError",
+        m.py().get_type::<InvalidRoundsError>(),
+    )?;
+    m.add(
+        "InvalidBlocksLengthError",
+        m.py().get_type::<InvalidBlocksLengthError>(),
+    )?;
+    m.add(
+        "InvalidLastBlockLengthError",
+        m.py().get_type::<InvalidLastBlockLengthError>(),
+    )?;
+    m.add(
+        "RoundBudgetExceededError",
+        m.py().get_type::<RoundBudgetExceededError>(),
+    )?;
+    m.add("OutOfGasError", m.py().get_type::<OutOfGasError>())?;
+    m.add_class::<PyBlake2b>()?;
+    m.add_class::<PyBlake2bParams>()?;
+    m.add_function(wrap_pyfunction!(blake2s_compress, m)?)?;
+    m.add_class::<PyBlake2s>()?;
+    m.add_class::<PyBlake2bp>()?;
+    m.add_class::<PyBlake2xb>()?;
+    m.add_class::<PyBlake2bTree>()?;
+    Ok(())
+}
@@ -0,0 +1,360 @@
+use crate::hasher::{Blake2b, KEYBYTES, OUTBYTES};
+use crate::params::Blake2bParams;
+
+/// The minimum number of message bytes for which leaves are hashed on separate threads.
+const PARALLEL_MIN_BYTES: usize = 1 << 20;
+
+/// A blake2b tree hash with a configurable fanout, maximal depth, leaf length and inner hash
+/// length.
+///
+/// The message is split into leaves of `leaf_length` bytes, which are hashed at depth 0 with
+/// their index as the node offset.  Each level above hashes the concatenated inner hashes of up
+/// to `fanout` consecutive nodes of the level below, until a single root node remains.  The last
+/// node of each level has the last node flag set.  Nodes output `inner_length` bytes, except for
+/// the root, which outputs the digest.
+///
+/// The root is always an inner node at depth 1 or more, even for a message which fits in one
+/// leaf.  A fanout of 0 means unlimited fanout, so that the root hashes all leaves.  When the
+/// maximal depth is reached, the root hashes all nodes of the level below it, whatever the
+/// fanout.  A maximal depth of 1 is sequential hashing of a message which fits in one leaf.
+///
+/// Leaf and inner hashes are exposed so that callers can keep the leaf hashes of a large object
+/// and rehash only the leaves which change.
+///
+/// See here: https://blake2.net/blake2.pdf (section 2.10)
+#[derive(Clone)]
+pub struct Blake2bTree {
+    params: Blake2bParams,
+    key: [u8; KEYBYTES],
+}
+
+impl Blake2bTree {
+    /// Create a tree hash from the digest length, fanout, maximal depth, leaf length, inner hash
+    /// length, salt and personalization string in `params`.  Node offsets, node depths and last
+    /// node flags in `params` are ignored.  If `key` is non-empty, every leaf is keyed with it.
+    ///
+    /// A tree of depth 2 or more must have a non-zero inner hash length and a fanout other than
+    /// 1.
+    pub fn new(params: &Blake2bParams, key: &[u8]) -> Result<Self, String> {
+        let params = params
+            .key_length(key.len())
+            .node_offset(0)
+            .node_depth(0)
+            .last_node(false);
+        params.validate()?;
+
+        if params.get_depth() > 1 {
+            if params.get_inner_length() == 0 {
+                return Err("inner length must be non-zero for a tree of depth 2 or more".into());
+            }
+            if params.get_fanout() == 1 {
+                return Err("fanout must not be 1 for a tree of depth 2 or more".into());
+            }
+        }
+
+        let mut padded_key = [0; KEYBYTES];
+        padded_key[..key.len()].copy_from_slice(key);
+
+        Ok(Self {
+            params,
+            key: padded_key,
+        })
+    }
+
+    fn key(&self) -> &[u8] {
+        &self.key[..self.params.get_key_length()]
+    }
+
+    /// The number of leaves into which a message of `len` bytes is split.  There is always at
+    /// least one leaf.
+    pub fn leaf_count(&self, len: u64) -> u64 {
+        match self.params.get_leaf_length() {
+            0 => 1,
+            leaf_length => len.div_ceil(leaf_length).max(1),
+        }
+    }
+
+    /// Hash a single node of the tree at depth `node_depth` and offset `node_offset`, whose input
+    /// is the concatenation of `inputs`.
+    fn hash_node(
+        &self,
+        node_depth: usize,
+        node_offset: u64,
+        last_node: bool,
+        is_root: bool,
+        inputs: &[&[u8]],
+    ) -> Vec<u8> {
+        let params = self
+            .params
+            .node_depth(node_depth)
+            .node_offset(node_offset)
+            .last_node(last_node);
+        let mut hasher = if node_depth == 0 {
+            Blake2b::with_params(&params, self.key()).unwrap()
+        } else {
+            Blake2b::with_params_without_key(&params, crate::hasher::ROUNDS).unwrap()
+        };
+        for input in inputs.iter() {
+            hasher.update(input);
+        }
+
+        if is_root {
+            hasher.finalize()
+        } else {
+            hasher.finalize_full()[..self.params.get_inner_length()].to_vec()
+        }
+    }
+
+    /// Hash the leaf at offset `index`, returning its inner hash.  `last_node` must be set for
+    /// the last leaf of the message.  Leaves are only hashed on their own for trees of depth 2 or
+    /// more.
+    pub fn hash_leaf(&self, index: u64, data: &[u8], last_node: bool) -> Result<Vec<u8>, String> {
+        if self.params.get_depth() == 1 {
+            return Err("a tree of depth 1 has no leaves apart from its root".into());
+        }
+        if self.params.get_leaf_length() != 0 && data.len() as u64 > self.params.get_leaf_length() {
+            return Err(format!(
+                "leaf data must have length at most {}, got: {}",
+                self.params.get_leaf_length(),
+                data.len(),
+            ));
+        }
+
+        Ok(self.hash_node(0, index, last_node, false, &[data]))
+    }
+
+    /// Calculate the digest from the inner hashes of all leaves, as returned by `hash_leaf`.
+    pub fn hash_from_leaves<H: AsRef<[u8]>>(&self, leaf_hashes: &[H]) -> Result<Vec<u8>, String> {
+        if self.params.get_depth() == 1 {
+            return Err("a tree of depth 1 has no leaves apart from its root".into());
+        }
+        if leaf_hashes.is_empty() {
+            return Err("a tree must have at least one leaf".into());
+        }
+        let inner_length = self.params.get_inner_length();
+        if let Some(leaf_hash) = leaf_hashes
+            .iter()
+            .find(|h| h.as_ref().len() != inner_length)
+        {
+            return Err(format!(
+                "leaf hashes must have length {}, got: {}",
+                inner_length,
+                leaf_hash.as_ref().len(),
+            ));
+        }
+
+        let fanout = match self.params.get_fanout() {
+            0 => usize::MAX,
+            fanout => fanout,
+        };
+
+        let mut level: Vec<Vec<u8>> = leaf_hashes.iter().map(|h| h.as_ref().to_vec()).collect();
+        let mut node_depth = 1;
+        loop {
+            if level.len() <= fanout || node_depth == self.params.get_depth() - 1 {
+                let inputs: Vec<&[u8]> = level.iter().map(|h| h.as_slice()).collect();
+                return Ok(self.hash_node(node_depth, 0, true, true, &inputs));
+            }
+
+            let node_count = level.len().div_ceil(fanout);
+            level = level
+                .chunks(fanout)
+                .enumerate()
+                .map(|(i, children)| {
+                    let inputs: Vec<&[u8]> = children.iter().map(|h| h.as_slice()).collect();
+                    self.hash_node(node_depth, i as u64, i == node_count - 1, false, &inputs)
+                })
+                .collect();
+            node_depth += 1;
+        }
+    }
+
+    /// Calculate the tree hash digest of `data`.  Leaves of large messages are hashed on
+    /// separate threads.
+    pub fn hash(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        if self.params.get_depth() == 1 {
+            if self.leaf_count(data.len() as u64) > 1 {
+                return Err(format!(
+                    "data must fit in a single leaf of a tree of depth 1, got length: {}",
+                    data.len(),
+                ));
+            }
+            return Ok(self.hash_node(0, 0, false, true, &[data]));
+        }
+
+        let leaf_count = self.leaf_count(data.len() as u64) as usize;
+        let leaf_length = match self.params.get_leaf_length() {
+            0 => data.len().max(1),
+            leaf_length => leaf_length as usize,
+        };
+        let leaf = |i: usize| {
+            let start = (i * leaf_length).min(data.len());
+            let end = ((i + 1) * leaf_length).min(data.len());
+            self.hash_node(
+                0,
+                i as u64,
+                i == leaf_count - 1,
+                false,
+                &[&data[start..end]],
+            )
+        };
+
+        let threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .min(leaf_count);
+        let leaf_hashes: Vec<Vec<u8>> = if data.len() < PARALLEL_MIN_BYTES || threads < 2 {
+            (0..leaf_count).map(leaf).collect()
+        } else {
+            let per_thread = leaf_count.div_ceil(threads);
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = (0..leaf_count)
+                    .step_by(per_thread)
+                    .map(|start| {
+                        let end = (start + per_thread).min(leaf_count);
+                        scope.spawn(move || (start..end).map(leaf).collect::<Vec<_>>())
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .flat_map(|handle| handle.join().unwrap())
+                    .collect()
+            })
+        };
+
+        self.hash_from_leaves(&leaf_hashes)
+    }
+}
+
+impl Default for Blake2bTree {
+    /// A binary tree of maximal depth 255 with 4 KiB leaves and 64-byte inner hashes.
+    fn default() -> Self {
+        let params = Blake2bParams::new()
+            .fanout(2)
+            .depth(255)
+            .leaf_length(4096)
+            .inner_length(OUTBYTES);
+
+        Self::new(&params, &[]).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(params: &Blake2bParams, input: &[u8], out_len: Option<usize>) -> Vec<u8> {
+        let mut hasher = Blake2b::with_params(params, &[]).unwrap();
+        hasher.update(input);
+        match out_len {
+            Some(n) => hasher.finalize_full()[..n].to_vec(),
+            None => hasher.finalize(),
+        }
+    }
+
+    #[test]
+    fn test_tree_matches_manual_composition() {
+        // A binary tree of maximal depth 3 over 5 leaves.  Level 1 has 3 nodes and the root at
+        // depth 2 hashes all of them since the maximal depth is reached.
+        let params = Blake2bParams::new()
+            .digest_length(32)
+            .fanout(2)
+            .depth(3)
+            .leaf_length(128)
+            .inner_length(48);
+        let data: Vec<u8> = (0..600).map(|i| i as u8).collect();
+
+        let leaves: Vec<Vec<u8>> = data
+            .chunks(128)
+            .enumerate()
+            .map(|(i, chunk)| {
+                let p = params.node_offset(i as u64).last_node(i == 4);
+                node(&p, chunk, Some(48))
+            })
+            .collect();
+        let level_1: Vec<Vec<u8>> = leaves
+            .chunks(2)
+            .enumerate()
+            .map(|(i, children)| {
+                let p = params.node_depth(1).node_offset(i as u64).last_node(i == 2);
+                node(&p, &children.concat(), Some(48))
+            })
+            .collect();
+        let root = node(
+            &params.node_depth(2).last_node(true),
+            &level_1.concat(),
+            None,
+        );
+
+        let tree = Blake2bTree::new(&params, &[]).unwrap();
+
+        assert_eq!(tree.hash(&data).unwrap(), root);
+        assert_eq!(tree.hash_from_leaves(&leaves).unwrap(), root);
+        assert_eq!(tree.hash_leaf(4, &data[512..], true).unwrap(), leaves[4]);
+    }
+
+    #[test]
+    fn test_tree_single_leaf_has_separate_root() {
+        let params = Blake2bParams::new()
+            .fanout(4)
+            .depth(2)
+            .leaf_length(1024)
+            .inner_length(64);
+        let leaf = node(&params.last_node(true), b"abc", Some(64));
+        let root = node(&params.node_depth(1).last_node(true), &leaf, None);
+
+        let tree = Blake2bTree::new(&params, &[]).unwrap();
+
+        assert_eq!(tree.hash(b"abc").unwrap(), root);
+    }
+
+    #[test]
+    fn test_tree_depth_1_is_sequential() {
+        let params = Blake2bParams::new().leaf_length(10);
+        let tree = Blake2bTree::new(&params, &[]).unwrap();
+
+        assert_eq!(tree.hash(b"abc").unwrap(), node(&params, b"abc", None),);
+        assert!(tree.hash(&[0; 11]).is_err());
+        assert!(tree.hash_leaf(0, b"abc", true).is_err());
+    }
+
+    #[test]
+    fn test_tree_parallel_leaves() {
+        let params = Blake2bParams::new()
+            .fanout(0)
+            .depth(2)
+            .leaf_length(4096)
+            .inner_length(64);
+        let tree = Blake2bTree::new(&params, &[]).unwrap();
+        let data: Vec<u8> = (0..PARALLEL_MIN_BYTES + 5000).map(|i| i as u8).collect();
+
+        let leaves: Vec<Vec<u8>> = data
+            .chunks(4096)
+            .enumerate()
+            .map(|(i, chunk)| {
+                let last_node = (i + 1) * 4096 >= data.len();
+                tree.hash_leaf(i as u64, chunk, last_node).unwrap()
+            })
+            .collect();
+
+        assert_eq!(
+            tree.hash(&data).unwrap(),
+            tree.hash_from_leaves(&leaves).unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_tree_invalid_configuration() {
+        let tree_params = Blake2bParams::new().depth(3).leaf_length(128);
+
+        assert!(Blake2bTree::new(&tree_params.fanout(2), &[]).is_err());
+        assert!(Blake2bTree::new(&tree_params.fanout(1).inner_length(64), &[]).is_err());
+        assert!(Blake2bTree::new(&tree_params.depth(0), &[]).is_err());
+
+        let tree = Blake2bTree::new(&tree_params.fanout(2).inner_length(64), &[]).unwrap();
+
+        assert!(tree.hash_leaf(0, &[0; 129], true).is_err());
+        assert!(tree.hash_from_leaves::<Vec<u8>>(&[]).is_err());
+        assert!(tree.hash_from_leaves(&[vec![0; 63]]).is_err());
+    }
+}
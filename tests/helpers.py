@@ -0,0 +1,28 @@
+"""
+Test vectors and helpers shared by the test modules.
+"""
+
+# The fifth test vector from EIP-152, with 12 rounds.
+INPUT = bytes.fromhex(
+    '0000000c48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b61626300000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000300000000000000000000000000000001'  # noqa: E501
+)
+
+
+def with_rounds(rounds):
+    """
+    Return ``INPUT`` with its number of rounds replaced by ``rounds``.
+    """
+    return rounds.to_bytes(4, 'big') + INPUT[4:]
+
+
+def raised(fn, *args, **kwargs):
+    """
+    Return the exception raised by calling ``fn`` with the given arguments,
+    failing if none is raised.
+    """
+    try:
+        fn(*args, **kwargs)
+    except BaseException as err:
+        return err
+    else:
+        assert False, 'error not raised'
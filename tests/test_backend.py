@@ -0,0 +1,7 @@
+import blake2b
+
+BACKENDS = ('avx2', 'sse4.1', 'scalar')
+
+
+def test_backend():
+    assert blake2b.backend() in BACKENDS
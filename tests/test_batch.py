@@ -0,0 +1,57 @@
+from hypothesis import (
+    given,
+    strategies as st,
+)
+
+import blake2b
+
+from .helpers import INPUT
+
+records = st.lists(
+    st.tuples(
+        st.integers(min_value=0, max_value=10000),
+        st.integers(min_value=0, max_value=2),
+    ).map(lambda x: x[0].to_bytes(4, 'big') + INPUT[4:212] + bytes([x[1]])),
+    max_size=50,
+)
+
+
+@given(records, st.integers(min_value=1, max_value=8))
+def test_batch_matches_decode_and_compress(inputs, threads):
+    results = blake2b.decode_and_compress_batch(inputs, threads=threads)
+
+    assert len(results) == len(inputs)
+    for input_bytes, result in zip(inputs, results):
+        if input_bytes[212] == 2:
+            assert isinstance(result, blake2b.InvalidFinalBlockFlagError)
+            assert result.value == 2
+        else:
+            assert result == blake2b.decode_and_compress(input_bytes)
+
+
+def test_batch_concatenated_buffer():
+    inputs = [INPUT, INPUT[:-1] + b'\x00', INPUT]
+    results = blake2b.decode_and_compress_batch(b''.join(inputs))
+
+    assert results == [blake2b.decode_and_compress(x) for x in inputs]
+
+
+def test_batch_invalid_records():
+    results = blake2b.decode_and_compress_batch([INPUT, INPUT[:-1]])
+
+    assert results[0] == blake2b.decode_and_compress(INPUT)
+    assert isinstance(results[1], blake2b.InvalidInputLengthError)
+    assert results[1].length == 212
+
+
+def test_batch_invalid_arguments():
+    for args, kwargs in (
+        ((INPUT + b'\x00',), {}),
+        (([INPUT],), dict(threads=0)),
+    ):
+        try:
+            blake2b.decode_and_compress_batch(*args, **kwargs)
+        except ValueError:
+            pass
+        else:
+            assert False, f'error not raised for {kwargs}'
@@ -0,0 +1,64 @@
+import hashlib
+
+from hypothesis import (
+    given,
+    strategies as st,
+)
+
+import blake2b
+
+
+def blake2bp_with_hashlib(message):
+    """
+    Compute a 64-byte BLAKE2bp digest by composing blake2b leaf and root hashes
+    with the tree hashing parameters of ``hashlib.blake2b``.
+    """
+    leaf_outputs = []
+    for i in range(4):
+        leaf_message = b''.join(
+            message[j:j + 128]
+            for j in range(i * 128, len(message), 512)
+        )
+        leaf_outputs.append(hashlib.blake2b(
+            leaf_message,
+            fanout=4,
+            depth=2,
+            node_offset=i,
+            inner_size=64,
+            last_node=(i == 3),
+        ).digest())
+
+    return hashlib.blake2b(
+        b''.join(leaf_outputs),
+        fanout=4,
+        depth=2,
+        node_depth=1,
+        inner_size=64,
+        last_node=True,
+    ).digest()
+
+
+@given(st.binary(max_size=4096), st.integers(min_value=1, max_value=700))
+def test_blake2bp_matches_hashlib_composition(message, chunk_size):
+    hasher = blake2b.Blake2bp()
+    for i in range(0, len(message), chunk_size):
+        hasher.update(message[i:i + chunk_size])
+
+    assert hasher.digest() == blake2bp_with_hashlib(message)
+
+
+def test_blake2bp_large_update_matches_hashlib_composition():
+    message = bytes(range(256)) * 4096 + b'tail'
+
+    assert blake2b.Blake2bp(message).digest() == blake2bp_with_hashlib(message)
+
+
+def test_blake2bp_keyed():
+    # The first keyed known-answer test from the blake2 reference
+    # implementation.
+    expected = (
+        '9d9461073e4eb640a255357b839f394b838c6ff57c9b686a3f76107c1066728f'
+        '3c9956bd785cbc3bf79dc2ab578c5a0c063b9d9c405848de1dbe821cd05c940a'
+    )
+
+    assert blake2b.Blake2bp(key=bytes(range(64))).hexdigest() == expected
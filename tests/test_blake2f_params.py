@@ -0,0 +1,98 @@
+import pickle
+
+from hypothesis import (
+    given,
+    strategies as st,
+)
+
+import blake2b
+
+from .helpers import INPUT
+
+u32 = st.integers(min_value=0, max_value=2 ** 32 - 1)
+u64 = st.integers(min_value=0, max_value=2 ** 64 - 1)
+
+
+def test_named_attributes():
+    params = blake2b.decode_parameters(INPUT)
+
+    assert isinstance(params, blake2b.Blake2FParams)
+    assert params.rounds == 12
+    assert params.state[0] == 0x6a09e667f2bdc948
+    assert params.block[0] == 0x636261
+    assert params.offset_counters == [3, 0]
+    assert params.final_block_flag is True
+
+
+def test_tuple_compatibility():
+    params = blake2b.decode_parameters(INPUT)
+    rounds, state, block, offset_counters, final_block_flag = params
+
+    assert len(params) == 5
+    assert params[0] == rounds == params.rounds
+    assert params[3] == offset_counters == params.offset_counters
+    assert params[-1] is final_block_flag is True
+    assert params[1:3] == (state, block)
+    assert params == tuple(params)
+    assert params != tuple(params)[:4]
+    assert blake2b.compress(*params) == blake2b.decode_and_compress(INPUT)
+
+
+@given(
+    u32,
+    st.lists(u64, min_size=8, max_size=8),
+    st.lists(u64, min_size=16, max_size=16),
+    st.lists(u64, min_size=2, max_size=2),
+    st.booleans(),
+)
+def test_encode_and_compress(rounds, state, block, offsets, flag):
+    params = blake2b.Blake2FParams(rounds % 64, state, block, offsets, flag)
+    encoded = params.encode()
+
+    assert encoded == blake2b.encode_parameters(*params)
+    assert blake2b.decode_parameters(encoded) == params
+    assert params.compress() == blake2b.compress(*params)
+
+
+def test_equality_and_hashing():
+    params = blake2b.decode_parameters(INPUT)
+    same = blake2b.Blake2FParams(*params)
+    other = blake2b.Blake2FParams(13, *params[1:])
+
+    assert params == same
+    assert hash(params) == hash(same)
+    assert params != other
+    assert len({params, same, other}) == 2
+    assert params != 'not params'
+
+
+def test_repr_and_pickle():
+    params = blake2b.decode_parameters(INPUT)
+
+    assert repr(params).startswith(
+        'Blake2FParams(rounds=12, state=[7640891576939301192, '
+    )
+    assert repr(params).endswith(
+        'offset_counters=[3, 0], final_block_flag=True)'
+    )
+    assert pickle.loads(pickle.dumps(params)) == params
+
+
+def test_frozen():
+    params = blake2b.decode_parameters(INPUT)
+
+    try:
+        params.rounds = 13
+    except AttributeError:
+        pass
+    else:
+        assert False, 'frozen params were modified'
+
+
+def test_invalid_vector_lengths():
+    try:
+        blake2b.Blake2FParams(12, [0] * 8, [0] * 15, [0] * 2, True)
+    except blake2b.InvalidBlockLengthError:
+        pass
+    else:
+        assert False, 'error not raised for short block'
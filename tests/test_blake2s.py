@@ -0,0 +1,81 @@
+import hashlib
+import struct
+
+from hypothesis import (
+    given,
+    strategies as st,
+)
+
+import blake2b
+
+messages = st.binary(max_size=1024)
+digest_sizes = st.integers(min_value=1, max_value=32)
+
+BLAKE2S_IV = (
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+    0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+)
+
+
+@given(
+    messages,
+    digest_sizes,
+    st.binary(max_size=32),
+    st.binary(max_size=8),
+    st.binary(max_size=8),
+    st.booleans(),
+)
+def test_blake2s_hasher_matches_hashlib(
+        message,
+        digest_size,
+        key,
+        salt,
+        person,
+        last_node,
+):
+    kwargs = dict(
+        digest_size=digest_size,
+        key=key,
+        salt=salt,
+        person=person,
+        last_node=last_node,
+    )
+    expected = hashlib.blake2s(message, **kwargs)
+    actual = blake2b.Blake2s(message, **kwargs)
+
+    assert actual.digest() == expected.digest()
+    assert actual.hexdigest() == expected.hexdigest()
+    assert actual.digest_size == expected.digest_size
+    assert actual.block_size == expected.block_size
+    assert actual.name == expected.name
+
+
+@given(st.binary(max_size=64))
+def test_blake2s_compress_single_block(message):
+    starting_state = list(BLAKE2S_IV)
+    starting_state[0] ^= 0x01010020
+    block = struct.unpack('<16I', message.ljust(64, b'\x00'))
+
+    result = blake2b.blake2s_compress(
+        10,
+        starting_state,
+        list(block),
+        [len(message), 0],
+        True,
+    )
+
+    assert result == hashlib.blake2s(message).digest()
+
+
+def test_blake2s_compress_invalid_lengths():
+    for args in (
+        (10, [0] * 7, [0] * 16, [0, 0], True),
+        (10, [0] * 8, [0] * 15, [0, 0], True),
+        (10, [0] * 8, [0] * 16, [0], True),
+    ):
+        try:
+            blake2b.blake2s_compress(*args)
+        except ValueError:
+            pass
+        else:
+            assert False, f'error not raised for {args}'
@@ -0,0 +1,136 @@
+import hashlib
+import struct
+
+from hypothesis import (
+    given,
+    strategies as st,
+)
+
+import blake2b
+from . import reference_impl
+
+UNKNOWN_LENGTH = 2 ** 32 - 1
+
+
+def blake2xb_reference(message, xof_length, out_length, key, salt, person):
+    """
+    Compute BLAKE2Xb output with ``hashlib.blake2b`` for the root hash and the
+    reference compression function for the expansion blocks, which have
+    parameters that ``hashlib`` does not accept.
+    """
+    root = hashlib.blake2b(
+        message,
+        key=key,
+        salt=salt,
+        person=person,
+        node_offset=xof_length << 32,
+    ).digest()
+
+    out = b''
+    for i in range((out_length + 63) // 64):
+        block_size = min(64, out_length - 64 * i)
+        parameter_block = (
+            bytes([block_size, 0, 0, 0])
+            + struct.pack('<III', 64, i, xof_length)
+            + bytes([0, 64])
+            + bytes(14)
+            + salt.ljust(16, b'\x00')
+            + person.ljust(16, b'\x00')
+        )
+        starting_state = [
+            iv ^ p
+            for iv, p in zip(
+                reference_impl.Blake2b.IV,
+                struct.unpack('<8Q', parameter_block),
+            )
+        ]
+        out += reference_impl.blake2b_compress(
+            12,
+            starting_state,
+            root.ljust(128, b'\x00'),
+            (64, 0),
+            True,
+        )[:block_size]
+
+    return out
+
+
+@given(
+    st.binary(max_size=300),
+    st.integers(min_value=1, max_value=1000),
+    st.binary(max_size=64),
+    st.binary(max_size=16),
+    st.binary(max_size=16),
+)
+def test_blake2xb_known_length(message, length, key, salt, person):
+    hasher = blake2b.Blake2xb(
+        message,
+        length=length,
+        key=key,
+        salt=salt,
+        person=person,
+    )
+    expected = blake2xb_reference(message, length, length, key, salt, person)
+
+    assert hasher.digest() == expected
+    assert hasher.hexdigest() == expected.hex()
+    assert hasher.read(length // 2) + hasher.read(length) == expected
+    assert hasher.read(1) == b''
+
+
+@given(st.binary(max_size=300), st.integers(min_value=1, max_value=1000))
+def test_blake2xb_unknown_length(message, length):
+    hasher = blake2b.Blake2xb(message)
+    expected = blake2xb_reference(
+        message, UNKNOWN_LENGTH, length, b'', b'', b'',
+    )
+
+    assert hasher.digest(length) == expected
+
+    # The reader hashes every block with a full digest length, so only whole
+    # blocks match the output of the reference implementation.
+    whole_blocks = length - length % 64
+    assert hasher.read(whole_blocks) == expected[:whole_blocks]
+
+
+def test_blake2xb_update_after_read():
+    hasher = blake2b.Blake2xb(b'abc', length=100)
+    hasher.read(10)
+
+    try:
+        hasher.update(b'def')
+    except ValueError:
+        pass
+    else:
+        assert False, 'error not raised for update after read'
+
+
+def test_blake2xb_invalid_lengths():
+    for kwargs in (dict(length=0), dict(length=UNKNOWN_LENGTH)):
+        try:
+            blake2b.Blake2xb(**kwargs)
+        except ValueError:
+            pass
+        else:
+            assert False, f'error not raised for {kwargs}'
+
+    for hasher, length in (
+        (blake2b.Blake2xb(length=100), 99),
+        (blake2b.Blake2xb(), None),
+        (blake2b.Blake2xb(), 0),
+        (blake2b.Blake2xb(b'a', length=10), 2 ** 62),
+        (blake2b.Blake2xb(b'a'), 2 ** 62),
+    ):
+        try:
+            hasher.digest(length)
+        except ValueError:
+            pass
+        else:
+            assert False, f'error not raised for digest({length})'
+
+
+def test_blake2xb_huge_read():
+    hasher = blake2b.Blake2xb(b'a', length=10)
+
+    assert hasher.read(2 ** 62) == hasher.digest()
+    assert hasher.read(2 ** 62) == b''
@@ -0,0 +1,155 @@
+import array
+import hashlib
+import mmap
+import threading
+
+import blake2b
+from . import reference_impl
+from .helpers import INPUT, with_rounds
+
+EXPECTED = blake2b.decode_and_compress(INPUT)
+
+# The same input with 2 ** 16 rounds, so that the GIL is released for long
+# enough for other threads to run.
+LONG_INPUT = with_rounds(2 ** 16)
+
+
+def byte_buffers(data):
+    """
+    Return ``data`` in each kind of object supporting the buffer protocol.
+    """
+    mapped = mmap.mmap(-1, len(data))
+    mapped.write(data)
+
+    return (
+        data,
+        bytearray(data),
+        memoryview(data),
+        memoryview(b'\x00' + data)[1:],
+        memoryview(bytes(x for b in data for x in (b, 0)))[::2],
+        mapped,
+        list(data),
+    )
+
+
+def test_decode_and_compress_buffers():
+    for buffer in byte_buffers(INPUT):
+        assert blake2b.decode_and_compress(buffer) == EXPECTED
+
+
+def test_compress_word_buffers():
+    rounds = int.from_bytes(INPUT[:4], 'big')
+    state, block, offsets = INPUT[4:68], INPUT[68:196], INPUT[196:212]
+
+    for args in (
+        (state, block, offsets),
+        (bytearray(state), memoryview(block), bytes(offsets)),
+        tuple(array.array('Q', x) for x in (state, block, offsets)),
+        tuple(memoryview(x).cast('Q') for x in (state, block, offsets)),
+        (
+            # Unaligned words are copied like those of any other mutable buffer.
+            memoryview(b'\x00' + state)[1:].cast('B').cast('Q'),
+            list(reference_impl.get_64_bit_little_endian_words(block)),
+            offsets,
+        ),
+    ):
+        assert blake2b.compress(rounds, *args, True) == EXPECTED
+
+
+def test_compress_word_bytes_length_error():
+    try:
+        blake2b.compress(12, bytes(63), bytes(128), bytes(16), True)
+    except ValueError:
+        pass
+    else:
+        assert False, 'error not raised for partial word'
+
+
+def test_hasher_buffers():
+    data = bytes(range(256)) * 3
+    expected = hashlib.blake2b(data, key=b'key', salt=b'salt').digest()
+
+    for buffer in byte_buffers(data):
+        hasher = blake2b.Blake2b(
+            key=memoryview(b'key'),
+            salt=bytearray(b'salt'),
+        )
+        hasher.update(buffer)
+
+        assert hasher.digest() == expected
+        assert hasher.verify(memoryview(expected))
+
+
+def flipped(data, offset):
+    """
+    Return ``data`` with the bits of the byte at ``offset`` flipped.
+    """
+    data = bytearray(data)
+    data[offset] ^= 0xff
+
+    return bytes(data)
+
+
+def check_while_mutating(fn, data, offset):
+    """
+    Check that ``fn`` gives the result for either ``data`` or ``data`` with
+    the byte at ``offset`` flipped when passed ``data`` in a ``bytearray`` or
+    a ``memoryview`` of one, while another thread keeps flipping that byte.
+    """
+    expected = (fn(data), fn(flipped(data, offset)))
+
+    for buffer in (bytearray(data), memoryview(bytearray(data))):
+        done = threading.Event()
+
+        def mutate():
+            while not done.is_set():
+                buffer[offset] ^= 0xff
+
+        thread = threading.Thread(target=mutate)
+        thread.start()
+        try:
+            for _ in range(20):
+                assert fn(buffer) in expected
+        finally:
+            done.set()
+            thread.join()
+
+
+def test_mutable_buffers_with_gil_released():
+    rounds = int.from_bytes(LONG_INPUT[:4], 'big')
+    state, block, offsets = LONG_INPUT[4:68], LONG_INPUT[68:196], LONG_INPUT[196:212]
+
+    for fn, data, offset in (
+        (blake2b.decode_and_compress, LONG_INPUT, 100),
+        (
+            lambda input: blake2b.decode_and_compress(input, check_interval=1000),
+            LONG_INPUT,
+            100,
+        ),
+        (lambda input: blake2b.decode_and_compress_batch(input), LONG_INPUT, 100),
+        (lambda input: blake2b.decode_and_compress_batch([input]), LONG_INPUT, 100),
+        (lambda input: blake2b.blake2f_precompile(input, rounds), LONG_INPUT, 100),
+        (lambda block: blake2b.compress(rounds, state, block, offsets, True), block, 32),
+        (
+            lambda block: blake2b.compress(
+                rounds, state, block, offsets, True, check_interval=1000,
+            ),
+            block,
+            32,
+        ),
+        (
+            lambda block: blake2b.compress_with_last_node(
+                rounds, state, block, offsets, True, True,
+            ),
+            block,
+            32,
+        ),
+        (
+            lambda block: blake2b.blake2s_compress(
+                rounds, state[:32], block[:64], offsets[:8], True,
+            ),
+            block[:64],
+            32,
+        ),
+    ):
+        check_while_mutating(fn, data, offset)
@@ -0,0 +1,125 @@
+import hashlib
+import threading
+
+from hypothesis import (
+    given,
+    strategies as st,
+)
+
+import blake2b
+
+rounds = st.integers(min_value=0, max_value=24)
+
+
+def pad(message):
+    """
+    Return ``message`` zero-padded to whole blocks along with the length of
+    its last block.
+    """
+    block_count = max(1, -(-len(message) // 128))
+
+    return (
+        message.ljust(block_count * 128, b'\x00'),
+        len(message) - (block_count - 1) * 128,
+    )
+
+
+@given(st.binary(max_size=600))
+def test_compress_blocks_matches_hashlib(message):
+    starting_state = blake2b.Blake2bParams().starting_state()
+    blocks, last_block_length = pad(message)
+
+    assert blake2b.compress_blocks(
+        12, starting_state, blocks, 0, last_block_length,
+    ) == hashlib.blake2b(message).digest()
+
+
+@given(
+    rounds,
+    st.binary(max_size=600),
+    st.integers(min_value=0, max_value=2 ** 128 - 1),
+)
+def test_compress_blocks_matches_compress(rounds, message, offset):
+    starting_state = blake2b.Blake2bParams().starting_state()
+    blocks, last_block_length = pad(message)
+    block_count = len(blocks) // 128
+
+    state = bytes(
+        b for word in starting_state for b in word.to_bytes(8, 'little')
+    )
+    t = offset
+    for i in range(block_count):
+        final = i == block_count - 1
+        t = (t + (last_block_length if final else 128)) % 2 ** 128
+        state = blake2b.compress(
+            rounds,
+            state,
+            blocks[i * 128:(i + 1) * 128],
+            [t % 2 ** 64, t >> 64],
+            final,
+        )
+
+    assert blake2b.compress_blocks(
+        rounds, starting_state, blocks, offset, last_block_length,
+    ) == state
+
+
+def test_compress_blocks_errors():
+    starting_state = blake2b.Blake2bParams().starting_state()
+
+    for blocks in (b'', bytes(127), bytes(129)):
+        try:
+            blake2b.compress_blocks(12, starting_state, blocks, 0, 0)
+        except blake2b.InvalidBlocksLengthError as err:
+            assert err.length == len(blocks)
+            assert err.block_size == 128
+        else:
+            assert False, 'error not raised for partial block'
+
+    try:
+        blake2b.compress_blocks(12, starting_state, bytes(128), 0, 129)
+    except blake2b.InvalidLastBlockLengthError as err:
+        assert err.length == 129
+        assert err.maximum == 128
+    else:
+        assert False, 'error not raised for long last block'
+
+    try:
+        blake2b.compress_blocks(12, starting_state[:7], bytes(128), 0, 0)
+    except blake2b.InvalidStartingStateLengthError:
+        pass
+    else:
+        assert False, 'error not raised for short starting state'
+
+
+def test_compress_blocks_mutable_buffers():
+    # Enough blocks and rounds that other threads run while the GIL is
+    # released, with the first and last bytes of the blocks flipped together
+    # by another thread.  Blocks read from the buffer as they are compressed
+    # would see one byte flipped and not the other.
+    starting_state = blake2b.Blake2bParams().starting_state()
+    message = bytes(range(256)) * 64
+    flipped = bytes([message[0] ^ 0xff]) + message[1:-1] + bytes([message[-1] ^ 0xff])
+    expected = tuple(
+        blake2b.compress_blocks(16384, starting_state, m, 0, 128)
+        for m in (message, flipped)
+    )
+
+    for blocks in (bytearray(message), memoryview(bytearray(message))):
+        done = threading.Event()
+
+        def mutate():
+            while not done.is_set():
+                blocks[:] = flipped
+                blocks[:] = message
+
+        thread = threading.Thread(target=mutate)
+        thread.start()
+        try:
+            for _ in range(10):
+                assert blake2b.compress_blocks(
+                    16384, starting_state, blocks, 0, 128,
+                ) in expected
+        finally:
+            done.set()
+            thread.join()
@@ -0,0 +1,92 @@
+import array
+import mmap
+
+import blake2b
+
+from .helpers import INPUT
+
+EXPECTED = blake2b.decode_and_compress(INPUT)
+
+STATE, BLOCK, OFFSETS = INPUT[4:68], INPUT[68:196], INPUT[196:212]
+
+
+def state_buffers(data):
+    """
+    Return ``data`` in each kind of writable buffer which may hold a state.
+    """
+    mapped = mmap.mmap(-1, len(data))
+    mapped.write(data)
+
+    return (
+        bytearray(data),
+        memoryview(bytearray(data)),
+        mapped,
+        array.array('Q', data),
+        memoryview(bytearray(data)).cast('Q'),
+    )
+
+
+def test_compress_into():
+    for out in state_buffers(bytes(64)):
+        assert blake2b.compress_into(out, 12, STATE, BLOCK, OFFSETS, True) is None
+        assert bytes(out) == EXPECTED
+
+
+def test_compress_in_place():
+    for state in state_buffers(STATE):
+        assert blake2b.compress_in_place(state, 12, BLOCK, OFFSETS, True) is None
+        assert bytes(state) == EXPECTED
+
+
+def test_compress_in_place_chains_blocks():
+    blocks = [bytes([i]) * 128 for i in range(4)]
+
+    expected = STATE
+    for i, block in enumerate(blocks):
+        expected = blake2b.compress(12, expected, block, [128 * (i + 1), 0], i == 3)
+
+    state = array.array('Q', STATE)
+    for i, block in enumerate(blocks):
+        blake2b.compress_in_place(state, 12, block, [128 * (i + 1), 0], i == 3)
+
+    assert state.tobytes() == expected
+
+
+def test_readonly_buffer_error():
+    for out in (bytes(64), memoryview(bytes(64))):
+        try:
+            blake2b.compress_into(out, 12, STATE, BLOCK, OFFSETS, True)
+        except TypeError:
+            pass
+        else:
+            assert False, 'error not raised for read-only buffer'
+
+
+def test_buffer_size_error():
+    for out in (bytearray(63), bytearray(65), array.array('Q', [0] * 7)):
+        try:
+            blake2b.compress_into(out, 12, STATE, BLOCK, OFFSETS, True)
+        except ValueError:
+            pass
+        else:
+            assert False, 'error not raised for wrong buffer size'
+
+        try:
+            blake2b.compress_in_place(out, 12, BLOCK, OFFSETS, True)
+        except ValueError:
+            pass
+        else:
+            assert False, 'error not raised for wrong buffer size'
+
+
+def test_invalid_vector_leaves_buffer_unchanged():
+    state = bytearray(STATE)
+
+    try:
+        blake2b.compress_in_place(state, 12, BLOCK[:120], OFFSETS, True)
+    except blake2b.InvalidBlockLengthError:
+        pass
+    else:
+        assert False, 'error not raised for short block'
+
+    assert bytes(state) == STATE
@@ -0,0 +1,45 @@
+from hypothesis import (
+    given,
+    strategies as st,
+)
+
+import blake2b
+
+u32 = st.integers(min_value=0, max_value=2 ** 32 - 1)
+u64 = st.integers(min_value=0, max_value=2 ** 64 - 1)
+
+
+@given(
+    u32,
+    st.lists(u64, min_size=8, max_size=8),
+    st.lists(u64, min_size=16, max_size=16),
+    st.lists(u64, min_size=2, max_size=2),
+    st.booleans(),
+)
+def test_encode_parameters_roundtrip(rounds, state, block, offsets, flag):
+    args = (rounds, state, block, offsets, flag)
+    encoded = blake2b.encode_parameters(*args)
+
+    assert len(encoded) == 213
+    assert encoded[:4] == rounds.to_bytes(4, 'big')
+    assert encoded[4:12] == state[0].to_bytes(8, 'little')
+    assert encoded[212] == flag
+    assert blake2b.decode_parameters(encoded) == args
+
+
+def test_encode_parameters_invalid_rounds():
+    try:
+        blake2b.encode_parameters(2 ** 32, [0] * 8, [0] * 16, [0] * 2, True)
+    except blake2b.InvalidRoundsError as err:
+        assert (err.rounds, err.maximum) == (2 ** 32, 2 ** 32 - 1)
+    else:
+        assert False, 'error not raised for too many rounds'
+
+
+def test_encode_parameters_invalid_vector_lengths():
+    try:
+        blake2b.encode_parameters(12, [0] * 8, [0] * 15, [0] * 2, True)
+    except blake2b.InvalidBlockLengthError as err:
+        assert (err.length, err.expected) == (15, 16)
+    else:
+        assert False, 'error not raised for invalid block length'
@@ -0,0 +1,56 @@
+import blake2b
+
+from .helpers import INPUT, raised
+
+
+def test_invalid_input_length():
+    for fn in (
+        blake2b.decode_parameters,
+        blake2b.decode_and_compress,
+        lambda input_bytes: blake2b.blake2f_precompile(input_bytes, 100),
+    ):
+        err = raised(fn, INPUT[:-1])
+
+        assert isinstance(err, blake2b.InvalidInputLengthError)
+        assert isinstance(err, blake2b.CompressError)
+        assert isinstance(err, ValueError)
+        assert (err.length, err.expected) == (212, 213)
+
+
+def test_invalid_final_block_flag():
+    for fn in (
+        blake2b.decode_parameters,
+        blake2b.decode_and_compress,
+        lambda input_bytes: blake2b.blake2f_precompile(input_bytes, 100),
+    ):
+        err = raised(fn, INPUT[:-1] + b'\x07')
+
+        assert isinstance(err, blake2b.InvalidFinalBlockFlagError)
+        assert (err.value, err.offset) == (7, 212)
+
+
+def test_invalid_vector_lengths():
+    state, block, offsets = [0] * 8, [0] * 16, [0] * 2
+
+    for args, error_class, length, expected in (
+        ((state[:7], block, offsets), blake2b.InvalidStartingStateLengthError, 7, 8),  # noqa: E501
+        ((state, block + [0], offsets), blake2b.InvalidBlockLengthError, 17, 16),  # noqa: E501
+        ((state, block, offsets[:1]), blake2b.InvalidOffsetCountersLengthError, 1, 2),  # noqa: E501
+    ):
+        for fn in (blake2b.compress, blake2b.blake2s_compress):
+            err = raised(fn, 12, *args, True)
+
+            assert isinstance(err, error_class)
+            assert (err.length, err.expected) == (length, expected)
+
+        err = raised(blake2b.compress_with_last_node, 12, *args, True, True)
+
+        assert isinstance(err, error_class)
+
+
+def test_out_of_gas_fields():
+    err = raised(blake2b.blake2f_precompile, INPUT, 11)
+
+    assert isinstance(err, blake2b.OutOfGasError)
+    assert not isinstance(err, blake2b.CompressError)
+    assert (err.gas_required, err.gas_limit) == (12, 11)
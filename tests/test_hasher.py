@@ -0,0 +1,183 @@
+import copy
+import hashlib
+import pickle
+
+from hypothesis import (
+    given,
+    strategies as st,
+)
+
+import blake2b
+from . import reference_impl
+
+messages = st.binary(max_size=1024)
+digest_sizes = st.integers(min_value=1, max_value=64)
+chunk_sizes = st.integers(min_value=1, max_value=300)
+
+
+@given(messages, digest_sizes)
+def test_hasher_matches_hashlib(message, digest_size):
+    expected = hashlib.blake2b(message, digest_size=digest_size).digest()
+
+    assert blake2b.Blake2b(message, digest_size=digest_size).digest() == expected
+
+
+@given(messages, chunk_sizes)
+def test_hasher_incremental_updates(message, chunk_size):
+    hasher = blake2b.Blake2b()
+    for i in range(0, len(message), chunk_size):
+        hasher.update(message[i:i + chunk_size])
+
+    assert hasher.digest() == hashlib.blake2b(message).digest()
+
+
+@given(
+    messages,
+    digest_sizes,
+    st.binary(max_size=64),
+    st.binary(max_size=16),
+    st.binary(max_size=16),
+    st.integers(min_value=0, max_value=255),
+    st.integers(min_value=1, max_value=255),
+    st.integers(min_value=0, max_value=2 ** 32 - 1),
+    st.integers(min_value=0, max_value=2 ** 64 - 1),
+    st.integers(min_value=0, max_value=255),
+    st.integers(min_value=0, max_value=64),
+    st.booleans(),
+)
+def test_hasher_parameters_match_hashlib(
+        message,
+        digest_size,
+        key,
+        salt,
+        person,
+        fanout,
+        depth,
+        leaf_size,
+        node_offset,
+        node_depth,
+        inner_size,
+        last_node,
+):
+    kwargs = dict(
+        digest_size=digest_size,
+        key=key,
+        salt=salt,
+        person=person,
+        fanout=fanout,
+        depth=depth,
+        leaf_size=leaf_size,
+        node_offset=node_offset,
+        node_depth=node_depth,
+        inner_size=inner_size,
+        last_node=last_node,
+    )
+    expected = hashlib.blake2b(message, **kwargs)
+    actual = blake2b.Blake2b(message, **kwargs)
+
+    assert actual.digest() == expected.digest()
+    assert actual.hexdigest() == expected.hexdigest()
+    assert actual.digest_size == expected.digest_size
+    assert actual.block_size == expected.block_size
+    assert actual.name == expected.name
+
+
+@given(st.binary(max_size=128), st.integers(min_value=0, max_value=2 ** 8 - 1))
+def test_hasher_custom_rounds_match_python_impl(message, rounds):
+    starting_state = list(reference_impl.Blake2b.IV)
+    starting_state[0] ^= 0x01010040
+    expected = reference_impl.blake2b_compress(
+        rounds,
+        starting_state,
+        message.ljust(128, b'\x00'),
+        (len(message), 0),
+        True,
+    )
+
+    assert blake2b.Blake2b(message, rounds=rounds).digest() == expected
+
+
+@given(messages, messages)
+def test_hasher_copy_is_independent(prefix, suffix):
+    hasher = blake2b.Blake2b(prefix)
+    copied = hasher.copy()
+    copied.update(suffix)
+
+    assert hasher.digest() == hashlib.blake2b(prefix).digest()
+    assert copied.digest() == hashlib.blake2b(prefix + suffix).digest()
+
+
+def test_hasher_invalid_parameters():
+    for kwargs in (
+        dict(digest_size=0),
+        dict(digest_size=65),
+        dict(key=b'\x00' * 65),
+        dict(salt=b'\x00' * 17),
+        dict(person=b'\x00' * 17),
+        dict(fanout=256),
+        dict(depth=0),
+        dict(leaf_size=2 ** 32),
+        dict(node_depth=256),
+        dict(inner_size=65),
+    ):
+        try:
+            blake2b.Blake2b(**kwargs)
+        except ValueError:
+            pass
+        else:
+            assert False, f'error not raised for {kwargs}'
+
+
+@given(messages, st.binary(min_size=1, max_size=64), digest_sizes)
+def test_keyed_hasher_verify(message, key, digest_size):
+    tag = hashlib.blake2b(message, key=key, digest_size=digest_size).digest()
+    hasher = blake2b.Blake2b(message, key=key, digest_size=digest_size)
+
+    assert hasher.verify(tag)
+    assert hasher.verify(tag[:1])
+    assert not hasher.verify(b'')
+    assert not hasher.verify(tag + b'\x00')
+    assert not hasher.verify(bytes([tag[0] ^ 1]) + tag[1:])
+
+
+@given(messages, st.integers(min_value=0, max_value=1000), digest_sizes)
+def test_hasher_pickle_resumes(message, split, digest_size):
+    hasher = blake2b.Blake2b(
+        message[:split],
+        digest_size=digest_size,
+        key=b'key',
+        last_node=True,
+    )
+    restored = pickle.loads(pickle.dumps(hasher))
+    restored.update(message[split:])
+
+    assert restored.digest() == hashlib.blake2b(
+        message,
+        digest_size=digest_size,
+        key=b'key',
+        last_node=True,
+    ).digest()
+    assert copy.deepcopy(restored).digest() == restored.digest()
+
+
+def test_hasher_state_format():
+    hasher = blake2b.Blake2b(b'abc', digest_size=32, rounds=20)
+    state = hasher.__getstate__()
+
+    assert len(state) == 220
+    assert state[:4] == bytes([1, 32, 3, 0])
+    assert state[4:12] == (20).to_bytes(8, 'little')
+    assert state[92:95] == b'abc'
+
+    restored = blake2b.Blake2b()
+    restored.__setstate__(state)
+
+    assert restored.digest() == hasher.digest()
+
+    for bad_state in (state[:-1], b'\x02' + state[1:]):
+        try:
+            restored.__setstate__(bad_state)
+        except ValueError:
+            pass
+        else:
+            assert False, 'error not raised for invalid state'
@@ -1,3 +1,5 @@
+import hashlib
+
 from hypothesis import (
     given,
     strategies as st,
@@ -48,3 +50,56 @@ def test_equivalence_with_python_impl(
     )
 
     assert python_result == rust_result
+
+
+@given(
+    rounds,
+    starting_states,
+    blocks,
+    offset_counters,
+    final_block_flags,
+)
+def test_compress_without_last_node_flag_matches_compress(
+        rounds,
+        starting_state,
+        block,
+        offset_counter,
+        final_block_flag,
+):
+    word_block = list(reference_impl.get_64_bit_little_endian_words(block))
+    args = (rounds, starting_state, word_block, offset_counter)
+
+    assert blake2b.compress_with_last_node(
+        *args, final_block_flag, False,
+    ) == blake2b.compress(*args, final_block_flag)
+
+
+@given(st.binary(max_size=128), st.integers(min_value=0, max_value=3))
+def test_compress_with_last_node_flag_matches_hashlib(message, node_offset):
+    params = blake2b.Blake2bParams(
+        fanout=4,
+        depth=2,
+        node_offset=node_offset,
+        inner_size=64,
+    )
+    word_block = list(reference_impl.get_64_bit_little_endian_words(
+        message.ljust(128, b'\x00'),
+    ))
+    result = blake2b.compress_with_last_node(
+        12,
+        params.starting_state(),
+        word_block,
+        [len(message), 0],
+        True,
+        True,
+    )
+    expected = hashlib.blake2b(
+        message,
+        fanout=4,
+        depth=2,
+        node_offset=node_offset,
+        inner_size=64,
+        last_node=True,
+    ).digest()
+
+    assert result == expected
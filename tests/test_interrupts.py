@@ -0,0 +1,76 @@
+import _thread
+import threading
+import time
+
+import blake2b
+
+from .helpers import raised, with_rounds
+
+# The fifth test vector from EIP-152 with 2 ** 32 - 1 rounds, which takes far
+# too long to finish unless interrupted.
+MAX_ROUNDS_INPUT = with_rounds(2 ** 32 - 1)
+INPUT = with_rounds(2 ** 12)
+
+
+def test_interrupt_raises_keyboard_interrupt():
+    timer = threading.Timer(0.1, _thread.interrupt_main)
+    timer.start()
+    start = time.monotonic()
+
+    err = raised(
+        blake2b.decode_and_compress,
+        MAX_ROUNDS_INPUT,
+        check_interval=1000,
+    )
+    timer.join()
+
+    assert isinstance(err, KeyboardInterrupt)
+    assert time.monotonic() - start < 5
+
+
+def test_timeout():
+    start = time.monotonic()
+
+    err = raised(blake2b.decode_and_compress, MAX_ROUNDS_INPUT, timeout=0.1)
+
+    assert isinstance(err, TimeoutError)
+    assert time.monotonic() - start < 5
+
+
+def test_round_budget():
+    err = raised(blake2b.decode_and_compress, MAX_ROUNDS_INPUT, max_rounds=100)
+
+    assert isinstance(err, blake2b.RoundBudgetExceededError)
+    assert (err.rounds, err.max_rounds) == (2 ** 32 - 1, 100)
+
+
+def test_progress_callback():
+    calls = []
+    result = blake2b.decode_and_compress(
+        INPUT,
+        check_interval=1000,
+        progress=lambda done, total: calls.append((done, total)),
+    )
+
+    assert result == blake2b.decode_and_compress(INPUT)
+    assert calls == [(i * 1000, 4096) for i in range(1, 5)]
+
+
+def test_progress_callback_abandons_compression():
+    class Abandon(Exception):
+        pass
+
+    def progress(done, total):
+        raise Abandon
+
+    args = blake2b.decode_parameters(MAX_ROUNDS_INPUT)
+    err = raised(blake2b.compress, *args, progress=progress)
+
+    assert isinstance(err, Abandon)
+
+
+def test_invalid_options():
+    for kwargs in (dict(check_interval=0), dict(timeout=-1.0)):
+        err = raised(blake2b.decode_and_compress, INPUT, **kwargs)
+
+        assert isinstance(err, ValueError)
@@ -0,0 +1,92 @@
+import hashlib
+
+from hypothesis import (
+    given,
+    strategies as st,
+)
+
+import blake2b
+from . import reference_impl
+
+
+@given(
+    st.binary(max_size=128),
+    st.integers(min_value=1, max_value=64),
+    st.binary(max_size=16),
+    st.binary(max_size=16),
+)
+def test_params_starting_state_matches_hashlib(
+        message,
+        digest_size,
+        salt,
+        person,
+):
+    params = blake2b.Blake2bParams(
+        digest_size=digest_size,
+        salt=salt,
+        person=person,
+    )
+    word_block = list(reference_impl.get_64_bit_little_endian_words(
+        message.ljust(128, b'\x00'),
+    ))
+    result = blake2b.compress(
+        12,
+        params.starting_state(),
+        word_block,
+        [len(message), 0],
+        True,
+    )
+    expected = hashlib.blake2b(
+        message,
+        digest_size=digest_size,
+        salt=salt,
+        person=person,
+    ).digest()
+
+    assert result[:digest_size] == expected
+
+
+def test_params_parameter_block():
+    params = blake2b.Blake2bParams(
+        digest_size=32,
+        key_size=10,
+        fanout=2,
+        depth=3,
+        leaf_size=4096,
+        node_offset=5,
+        node_depth=1,
+        inner_size=64,
+        salt=b'salty',
+        person=b'me',
+    )
+
+    assert params.parameter_block() == (
+        bytes([32, 10, 2, 3])
+        + (4096).to_bytes(4, 'little')
+        + (5).to_bytes(8, 'little')
+        + bytes([1, 64])
+        + bytes(14)
+        + b'salty'.ljust(16, b'\x00')
+        + b'me'.ljust(16, b'\x00')
+    )
+
+
+def test_params_invalid():
+    for kwargs in (
+        dict(digest_size=0),
+        dict(digest_size=65),
+        dict(key_size=65),
+        dict(salt=b'\x00' * 17),
+        dict(person=b'\x00' * 17),
+        dict(fanout=256),
+        dict(depth=0),
+        dict(leaf_size=2 ** 32),
+        dict(node_depth=256),
+        dict(inner_size=65),
+    ):
+        try:
+            blake2b.Blake2bParams(**kwargs)
+        except ValueError:
+            pass
+        else:
+            assert False, f'error not raised for {kwargs}'
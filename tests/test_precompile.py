@@ -0,0 +1,48 @@
+from hypothesis import (
+    given,
+    strategies as st,
+)
+
+import blake2b
+
+from .helpers import INPUT
+
+
+@given(
+    st.integers(min_value=0, max_value=1000),
+    st.integers(min_value=0, max_value=2000),
+)
+def test_precompile_charges_one_gas_per_round(rounds, gas_limit):
+    input_bytes = rounds.to_bytes(4, 'big') + INPUT[4:]
+
+    try:
+        gas_used, output = blake2b.blake2f_precompile(input_bytes, gas_limit)
+    except blake2b.OutOfGasError:
+        assert rounds > gas_limit
+    else:
+        assert rounds <= gas_limit
+        assert gas_used == rounds
+        assert output == blake2b.decode_and_compress(input_bytes)
+
+
+def test_precompile_max_rounds_out_of_gas():
+    input_bytes = b'\xff' * 4 + INPUT[4:]
+
+    try:
+        blake2b.blake2f_precompile(input_bytes, 30_000_000)
+    except blake2b.OutOfGasError:
+        pass
+    else:
+        assert False, 'out of gas error not raised'
+
+
+def test_precompile_invalid_input():
+    for input_bytes in (INPUT[:-1], INPUT[:-1] + b'\x02'):
+        try:
+            blake2b.blake2f_precompile(input_bytes, 2 ** 64 - 1)
+        except blake2b.OutOfGasError:
+            assert False, 'out of gas error raised for invalid input'
+        except ValueError:
+            pass
+        else:
+            assert False, 'error not raised for invalid input'
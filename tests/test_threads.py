@@ -0,0 +1,48 @@
+import threading
+import time
+
+import blake2b
+
+from .helpers import with_rounds
+
+# The fifth test vector from EIP-152 with 2 ** 22 rounds, which takes long
+# enough for other threads to be scheduled many times.
+INPUT = with_rounds(2 ** 22)
+
+
+def count_while_running(fn):
+    """
+    Run ``fn`` on a separate thread and return the number of times the
+    current thread manages to count while it runs.
+    """
+    done = threading.Event()
+
+    def run():
+        try:
+            fn()
+        finally:
+            done.set()
+
+    thread = threading.Thread(target=run)
+    thread.start()
+
+    count = 0
+    while not done.is_set():
+        count += 1
+        time.sleep(0.001)
+    thread.join()
+
+    return count
+
+
+def test_decode_and_compress_releases_gil():
+    count = count_while_running(lambda: blake2b.decode_and_compress(INPUT))
+
+    assert count > 10
+
+
+def test_compress_releases_gil():
+    args = blake2b.decode_parameters(INPUT)
+    count = count_while_running(lambda: blake2b.compress(*args))
+
+    assert count > 10
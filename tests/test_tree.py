@@ -0,0 +1,113 @@
+import hashlib
+
+from hypothesis import (
+    given,
+    strategies as st,
+)
+
+import blake2b
+
+
+def tree_with_hashlib(message, fanout, depth, leaf_size, inner_size):
+    """
+    Compute a tree hash digest by composing blake2b node hashes with the tree
+    hashing parameters of ``hashlib.blake2b``.
+    """
+    params = dict(
+        fanout=fanout,
+        depth=depth,
+        leaf_size=leaf_size,
+        inner_size=inner_size,
+    )
+    leaves = [
+        message[i:i + leaf_size]
+        for i in range(0, len(message), leaf_size)
+    ] or [b'']
+    level = [
+        hashlib.blake2b(
+            leaf,
+            digest_size=inner_size,
+            node_offset=i,
+            last_node=(i == len(leaves) - 1),
+            **params,
+        ).digest()
+        for i, leaf in enumerate(leaves)
+    ]
+
+    node_depth = 1
+    while len(level) > (fanout or len(level)) and node_depth < depth - 1:
+        nodes = [level[i:i + fanout] for i in range(0, len(level), fanout)]
+        level = [
+            hashlib.blake2b(
+                b''.join(children),
+                digest_size=inner_size,
+                node_offset=i,
+                node_depth=node_depth,
+                last_node=(i == len(nodes) - 1),
+                **params,
+            ).digest()
+            for i, children in enumerate(nodes)
+        ]
+        node_depth += 1
+
+    return hashlib.blake2b(
+        b''.join(level),
+        node_depth=node_depth,
+        last_node=True,
+        **params,
+    ).digest()
+
+
+@given(
+    st.binary(max_size=2048),
+    st.sampled_from([0, 2, 3, 4]),
+    st.integers(min_value=2, max_value=5),
+    st.sampled_from([64, 100, 128]),
+)
+def test_tree_matches_hashlib_composition(message, fanout, depth, leaf_size):
+    tree = blake2b.Blake2bTree(
+        fanout=fanout,
+        depth=depth,
+        leaf_size=leaf_size,
+        inner_size=64,
+    )
+
+    assert tree.hash(message) == tree_with_hashlib(
+        message, fanout, depth, leaf_size, 64,
+    )
+
+
+def test_tree_from_leaves():
+    tree = blake2b.Blake2bTree(fanout=2, depth=4, leaf_size=128)
+    message = bytes(range(256)) * 5
+    leaves = [message[i:i + 128] for i in range(0, len(message), 128)]
+
+    leaf_hashes = [
+        tree.hash_leaf(i, leaf, i == len(leaves) - 1)
+        for i, leaf in enumerate(leaves)
+    ]
+
+    assert tree.hash_from_leaves(leaf_hashes) == tree.hash(message)
+
+
+def test_tree_large_message_matches_hashlib_composition():
+    message = bytes(range(256)) * 8192 + b'tail'
+    tree = blake2b.Blake2bTree(fanout=4, depth=3, leaf_size=4096)
+
+    assert tree.hash(message) == tree_with_hashlib(message, 4, 3, 4096, 64)
+
+
+def test_tree_invalid_parameters():
+    for kwargs in (
+        dict(fanout=1, depth=2),
+        dict(depth=2, inner_size=0),
+        dict(depth=0),
+        dict(digest_size=65),
+        dict(key=b'\x00' * 65),
+    ):
+        try:
+            blake2b.Blake2bTree(**kwargs)
+        except ValueError:
+            pass
+        else:
+            assert False, f'error not raised for {kwargs}'